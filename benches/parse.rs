@@ -0,0 +1,60 @@
+//! Parser throughput on large generated files (the load-test case: tens of thousands of entries).
+//!
+//! Run with `cargo bench --bench parse`; compare two runs with `--save-baseline` / `--baseline`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use ruster_env::parser::{parse_env_path, parse_env_str, parse_env_str_with, ParseOptions};
+use std::fmt::Write;
+
+/// Only plain `KEY=value` lines: nothing to unquote or interpolate
+fn plain(entries: usize) -> String {
+    let mut content = String::new();
+    for i in 0..entries {
+        writeln!(content, "SERVICE_{}_URL=http://service-{}.internal:8080/api/v1", i, i).unwrap();
+    }
+    content
+}
+
+/// What generated files tend to look like: comments, exports, quotes, escapes and references
+fn mixed(entries: usize) -> String {
+    let mut content = String::from("# generated\nBASE_HOST=example.internal\nBASE_PORT=8080\n");
+    for i in 0..entries {
+        match i % 6 {
+            0 => writeln!(content, "# group {}", i / 6),
+            1 => writeln!(content, "export WORKER_{}_THREADS={}", i, i % 32),
+            2 => writeln!(content, "WORKER_{}_NAME=\"worker {}\\tpool\" # inline comment", i, i),
+            3 => writeln!(content, "WORKER_{}_SECRET='s3cr3t-${{not-expanded}}-{}'", i, i),
+            4 => writeln!(content, "WORKER_{}_URL=http://${{BASE_HOST}}:$BASE_PORT/w/{}", i, i),
+            _ => writeln!(content, "WORKER_{}_LEVEL=${{LOG_LEVEL_{}:-info}}", i, i),
+        }
+        .unwrap();
+    }
+    content
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_env_str");
+    for entries in [1_000, 10_000, 50_000] {
+        for (name, content) in [("plain", plain(entries)), ("mixed", mixed(entries))] {
+            group.throughput(Throughput::Bytes(content.len() as u64));
+            group.bench_with_input(BenchmarkId::new(name, entries), &content, |b, content| {
+                b.iter(|| parse_env_str(black_box(content)).unwrap())
+            });
+        }
+    }
+    group.finish();
+
+    let content = mixed(10_000);
+    let options = ParseOptions { order_independent: true, ..Default::default() };
+    c.bench_function("parse_env_str/order_independent/mixed/10000", |b| {
+        b.iter(|| parse_env_str_with(black_box(&content), &options).unwrap())
+    });
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(".env");
+    std::fs::write(&path, mixed(10_000)).unwrap();
+    c.bench_function("parse_env_path/mixed/10000", |b| b.iter(|| parse_env_path(black_box(&path)).unwrap()));
+}
+
+criterion_group!(benches, bench_parse);
+criterion_main!(benches);
@@ -0,0 +1,518 @@
+//! Deserializes the variables of a .env file into a typed config struct (feature `serde`).
+//!
+//! Keys map to fields case-insensitively (`DB_HOST` fills `db_host`, or a field renamed to
+//! `DB_HOST` with serde attributes). Nested structs read the keys under their name and the
+//! separator: `DB__HOST` fills `db.host`.
+//! Numbers and bools are parsed from the text, `Option` fields may be missing or empty,
+//! and `Vec` fields split the value on commas.
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+
+use crate::parser::{self, EnvVar, ParseError, ParseOptions};
+
+/// Knobs for `from_path_with` and `from_str_with`
+#[derive(Debug, Clone)]
+pub struct FromEnvOptions {
+    /// Only keys starting with this are used, with the prefix removed (e.g. `APP_`)
+    pub prefix: Option<String>,
+    /// Splits a key into nested struct fields (`DB__HOST` -> `db.host`)
+    pub separator: String,
+    pub parse: ParseOptions,
+}
+
+impl Default for FromEnvOptions {
+    fn default() -> Self {
+        FromEnvOptions { prefix: None, separator: "__".to_string(), parse: ParseOptions::default() }
+    }
+}
+
+/// Why a file could not be turned into the requested type
+#[derive(Debug)]
+pub enum Error {
+    /// The file itself could not be parsed
+    Parse(ParseError),
+    /// A value does not fit its field, e.g. `PORT=eighty` for a `u16`
+    Value { key: String, path: PathBuf, line: usize, message: String },
+    /// A required field has no variable
+    Missing { key: String },
+    /// Any other complaint from the target type
+    Custom(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse(err) => write!(f, "{}", err),
+            Error::Value { key, path, line, message } => {
+                write!(f, "{}:{}: invalid value for {}: {}", path.display(), line, key, message)
+            }
+            Error::Missing { key } => write!(f, "missing variable {}", key),
+            Error::Custom(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Parse(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string())
+    }
+
+    /// Holds the bare field name until `visit_children` turns it into the full key
+    fn missing_field(field: &'static str) -> Self {
+        Error::Missing { key: field.to_string() }
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Self {
+        Error::Parse(err)
+    }
+}
+
+/// Parses the file at `path` and deserializes its variables into `T`
+pub fn from_path<T: DeserializeOwned, P: AsRef<Path>>(path: P) -> Result<T, Error> {
+    from_path_with(path, &FromEnvOptions::default())
+}
+
+pub fn from_path_with<T: DeserializeOwned, P: AsRef<Path>>(path: P, options: &FromEnvOptions) -> Result<T, Error> {
+    let output = parser::parse_env_path_with(path, &options.parse)?;
+    from_vars(output.vars, options)
+}
+
+/// Parses .env content held in memory and deserializes it into `T`
+pub fn from_str<T: DeserializeOwned>(content: &str) -> Result<T, Error> {
+    from_str_with(content, &FromEnvOptions::default())
+}
+
+pub fn from_str_with<T: DeserializeOwned>(content: &str, options: &FromEnvOptions) -> Result<T, Error> {
+    let output = parser::parse_env_str_with(content, &options.parse)?;
+    from_vars(output.vars, options)
+}
+
+/// Deserializes already parsed variables (for example the result of `parse_env_path`)
+pub fn from_vars<T: DeserializeOwned>(vars: Vec<EnvVar>, options: &FromEnvOptions) -> Result<T, Error> {
+    let mut root = Node::Map(BTreeMap::new());
+    for var in vars {
+        let name = match &options.prefix {
+            Some(prefix) => match var.key.strip_prefix(prefix.as_str()) {
+                Some(rest) => rest.to_string(),
+                None => continue,
+            },
+            None => var.key.clone(),
+        };
+        let segments: Vec<String> = name.split(options.separator.as_str()).map(|s| s.to_ascii_lowercase()).collect();
+        root.insert(&segments, var);
+    }
+    T::deserialize(NodeDeserializer { node: root, key: String::new(), options })
+}
+
+/// The variables arranged by separator: a leaf is one variable, a map holds nested fields
+enum Node {
+    Leaf(EnvVar),
+    Map(BTreeMap<String, Node>),
+}
+
+impl Node {
+    fn insert(&mut self, segments: &[String], var: EnvVar) {
+        let Node::Map(children) = self else {
+            // "DB=x" and "DB__HOST=y": the plain value wins the slot, the nested key is dropped
+            return;
+        };
+        match segments {
+            [] => {}
+            [last] => {
+                children.insert(last.clone(), Node::Leaf(var));
+            }
+            [first, rest @ ..] => {
+                children.entry(first.clone()).or_insert_with(|| Node::Map(BTreeMap::new())).insert(rest, var);
+            }
+        }
+    }
+}
+
+struct NodeDeserializer<'o> {
+    node: Node,
+    /// The env key this node stands for (without the prefix), used in `Missing` errors
+    key: String,
+    options: &'o FromEnvOptions,
+}
+
+/// The env key of `field` under `parent`, e.g. `DB` + `host` -> `DB__HOST`
+fn child_key(parent: &str, field: &str, separator: &str) -> String {
+    let field = field.to_ascii_uppercase();
+    if parent.is_empty() {
+        field
+    } else {
+        format!("{}{}{}", parent, separator, field)
+    }
+}
+
+macro_rules! forward_to_leaf {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match self.node {
+                    Node::Leaf(var) => ValueDeserializer { var }.$method(visitor),
+                    Node::Map(_) => Err(Error::Custom(format!(
+                        "expected a single value for {}, found nested keys",
+                        self.key
+                    ))),
+                }
+            }
+        )*
+    };
+}
+
+impl NodeDeserializer<'_> {
+    /// Visits the children of a map node. Keys matching one of `fields` regardless of case are
+    /// handed over as that field name, so renamed fields like `DATABASE_URL` are found too.
+    fn visit_children<'de, V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let children = match self.node {
+            Node::Map(children) => children,
+            Node::Leaf(var) => {
+                return Err(Error::Value {
+                    message: format!("expected nested keys such as {}{}FIELD", var.key, self.options.separator),
+                    key: var.key,
+                    path: var.path,
+                    line: var.line,
+                })
+            }
+        };
+        let nested_failed = Cell::new(false);
+        let access = NodeMap {
+            iter: children.into_iter(),
+            pending: None,
+            fields,
+            key: &self.key,
+            options: self.options,
+            nested_failed: &nested_failed,
+        };
+        visitor.visit_map(access).map_err(|err| match err {
+            // Only this level knows the full name of its own missing field; a nested
+            // struct's `Missing` already carries the full key
+            Error::Missing { key } if !nested_failed.get() => {
+                let prefix = self.options.prefix.as_deref().unwrap_or("");
+                Error::Missing { key: format!("{}{}", prefix, child_key(&self.key, &key, &self.options.separator)) }
+            }
+            other => other,
+        })
+    }
+}
+
+impl<'de> de::Deserializer<'de> for NodeDeserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.node {
+            Node::Leaf(var) => ValueDeserializer { var }.deserialize_any(visitor),
+            Node::Map(_) => self.deserialize_map(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.node {
+            Node::Leaf(var) => ValueDeserializer { var }.deserialize_option(visitor),
+            Node::Map(_) => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.visit_children(&[], visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.visit_children(fields, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.node {
+            Node::Leaf(var) => ValueDeserializer { var }.deserialize_enum(name, variants, visitor),
+            Node::Map(_) => Err(Error::Custom(format!("expected a single value for {}", self.key))),
+        }
+    }
+
+    forward_to_leaf! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+        deserialize_f32 deserialize_f64 deserialize_char deserialize_str deserialize_string
+        deserialize_bytes deserialize_byte_buf deserialize_unit deserialize_seq deserialize_identifier
+    }
+
+    /// Skipped keys may be a value or nested keys alike
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+}
+
+struct NodeMap<'k, 'o> {
+    iter: std::collections::btree_map::IntoIter<String, Node>,
+    pending: Option<(String, Node)>,
+    /// Field names of the struct being filled; empty for a plain map
+    fields: &'static [&'static str],
+    /// Key of the map itself, e.g. `DB` for the fields under `DB__`
+    key: &'k str,
+    options: &'o FromEnvOptions,
+    /// Set when a value failed, so its error is passed up unchanged
+    nested_failed: &'k Cell<bool>,
+}
+
+impl<'de> MapAccess<'de> for NodeMap<'_, '_> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        let Some((name, node)) = self.iter.next() else {
+            return Ok(None);
+        };
+        let field = self.fields.iter().copied().find(|field| field.eq_ignore_ascii_case(&name));
+        let key = seed.deserialize(IntoDeserializer::<Error>::into_deserializer(field.unwrap_or(&name)))?;
+        self.pending = Some((name, node));
+        Ok(Some(key))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (name, node) = self.pending.take().expect("next_value_seed called before next_key_seed");
+        let key = child_key(self.key, &name, &self.options.separator);
+        seed.deserialize(NodeDeserializer { node, key, options: self.options })
+            .inspect_err(|_| self.nested_failed.set(true))
+    }
+}
+
+/// One variable's text, converted to whatever the field asks for
+struct ValueDeserializer {
+    var: EnvVar,
+}
+
+impl ValueDeserializer {
+    /// Attaches the variable's key and line to an error that has no location yet
+    fn locate(&self, err: Error) -> Error {
+        match err {
+            Error::Custom(message) => Error::Value {
+                key: self.var.key.clone(),
+                path: self.var.path.clone(),
+                line: self.var.line,
+                message,
+            },
+            other => other,
+        }
+    }
+
+    fn invalid(&self, expected: &str) -> Error {
+        self.locate(Error::Custom(format!("expected {}, found '{}'", expected, self.var.value)))
+    }
+}
+
+macro_rules! parse_number {
+    ($($method:ident => $visit:ident: $ty:ty,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match self.var.value.trim().parse::<$ty>() {
+                    Ok(n) => visitor.$visit(n).map_err(|e| self.locate(e)),
+                    Err(_) => Err(self.invalid(stringify!($ty))),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let value = self.var.value.clone();
+        visitor.visit_string(value).map_err(|e| self.locate(e))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let value = match self.var.value.trim().to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => true,
+            "false" | "0" | "no" | "off" => false,
+            _ => return Err(self.invalid("a boolean (true/false, 1/0, yes/no, on/off)")),
+        };
+        visitor.visit_bool(value).map_err(|e| self.locate(e))
+    }
+
+    parse_number! {
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_i128 => visit_i128: i128,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_u128 => visit_u128: u128,
+        deserialize_f32 => visit_f32: f32,
+        deserialize_f64 => visit_f64: f64,
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let mut chars = self.var.value.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => visitor.visit_char(c).map_err(|e| self.locate(e)),
+            _ => Err(self.invalid("a single character")),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let bytes = self.var.value.clone().into_bytes();
+        visitor.visit_byte_buf(bytes).map_err(|e| self.locate(e))
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    /// `KEY=` counts as absent, like an unset variable
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.var.value.trim().is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit().map_err(|e| self.locate(e))
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// "a, b,c" -> ["a", "b", "c"]; an empty value is an empty list
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let items: Vec<EnvVar> = if self.var.value.trim().is_empty() {
+            Vec::new()
+        } else {
+            self.var
+                .value
+                .split(',')
+                .map(|item| EnvVar { value: item.trim().to_string(), ..self.var.clone() })
+                .collect()
+        };
+        visitor.visit_seq(ValueSeq { iter: items.into_iter() }).map_err(|e| self.locate(e))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(self.invalid("nested keys, not a single value"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    /// Unit variants by name: `LOG_LEVEL=debug`
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let value = self.var.value.trim().to_string();
+        let variant: de::value::StringDeserializer<Error> = value.into_deserializer();
+        visitor.visit_enum(variant).map_err(|e| self.locate(e))
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+}
+
+struct ValueSeq {
+    iter: std::vec::IntoIter<EnvVar>,
+}
+
+impl<'de> SeqAccess<'de> for ValueSeq {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Error> {
+        match self.iter.next() {
+            Some(var) => seed.deserialize(ValueDeserializer { var }).map(Some),
+            None => Ok(None),
+        }
+    }
+}
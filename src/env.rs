@@ -0,0 +1,234 @@
+//! `Env`: the variables of a file (or the process) as an ordered key -> value map.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::parser::EnvVar;
+
+/// Variables in the order their keys were first added. Each key appears once;
+/// inserting an existing key replaces its value in place.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Env {
+    vars: Vec<(String, String)>,
+    /// Position of each key in `vars`
+    index: HashMap<String, usize>,
+}
+
+/// Which value `Env::merge` keeps when both sides define a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergePolicy {
+    /// The merged-in value replaces the existing one, like a later file overriding an earlier one
+    #[default]
+    Override,
+    /// The existing value stays; only new keys are added (the CLI's `--no-overwrite`)
+    KeepExisting,
+}
+
+/// One difference reported by `Env::diff`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Added { key: String, value: String },
+    Removed { key: String, value: String },
+    Changed { key: String, old: String, new: String },
+}
+
+impl Change {
+    pub fn key(&self) -> &str {
+        match self {
+            Change::Added { key, .. } | Change::Removed { key, .. } | Change::Changed { key, .. } => key,
+        }
+    }
+}
+
+impl Env {
+    pub fn new() -> Self {
+        Env::default()
+    }
+
+    /// The current process environment. Variables whose name or value is not valid
+    /// Unicode are left out, as `std::env::var` would report them missing.
+    pub fn from_system() -> Self {
+        std::env::vars_os()
+            .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.vars.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vars.is_empty()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.index.get(key).map(|&idx| self.vars[idx].1.as_str())
+    }
+
+    /// Parses the value of `key`; `Ok(None)` if the key is not set
+    pub fn get_as<T: FromStr>(&self, key: &str) -> Result<Option<T>, T::Err> {
+        self.get(key).map(str::parse).transpose()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Sets `key`, keeping its position if it already exists. Returns the previous value.
+    pub fn insert<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) -> Option<String> {
+        let key = key.into();
+        let value = value.into();
+        match self.index.get(&key) {
+            Some(&idx) => Some(std::mem::replace(&mut self.vars[idx].1, value)),
+            None => {
+                self.index.insert(key.clone(), self.vars.len());
+                self.vars.push((key, value));
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let idx = self.index.remove(key)?;
+        let (_, value) = self.vars.remove(idx);
+        self.reindex();
+        Some(value)
+    }
+
+    /// Key/value pairs in order
+    pub fn iter(&self) -> Iter<'_> {
+        Iter(self.vars.iter())
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.vars.iter().map(|(key, _)| key.as_str())
+    }
+
+    /// Adds every variable of `other`. New keys go to the end in `other`'s order;
+    /// `policy` decides what happens to keys both define.
+    pub fn merge(&mut self, other: Env, policy: MergePolicy) {
+        for (key, value) in other.vars {
+            if policy == MergePolicy::KeepExisting && self.contains_key(&key) {
+                continue;
+            }
+            self.insert(key, value);
+        }
+    }
+
+    /// What changes from `self` to `other`: changed and removed keys in `self`'s order,
+    /// then added keys in `other`'s order
+    pub fn diff(&self, other: &Env) -> Vec<Change> {
+        let mut changes = Vec::new();
+        for (key, value) in self.iter() {
+            match other.get(key) {
+                None => changes.push(Change::Removed { key: key.to_string(), value: value.to_string() }),
+                Some(new) if new != value => changes.push(Change::Changed {
+                    key: key.to_string(),
+                    old: value.to_string(),
+                    new: new.to_string(),
+                }),
+                Some(_) => {}
+            }
+        }
+        for (key, value) in other.iter().filter(|(key, _)| !self.contains_key(key)) {
+            changes.push(Change::Added { key: key.to_string(), value: value.to_string() });
+        }
+        changes
+    }
+
+    /// Keeps only the variables for which `keep` returns true
+    pub fn retain<F: FnMut(&str, &str) -> bool>(&mut self, mut keep: F) {
+        self.vars.retain(|(key, value)| keep(key, value));
+        self.reindex();
+    }
+
+    /// The variables whose key starts with `prefix`, with the prefix removed (`APP_PORT` -> `PORT`)
+    pub fn with_prefix(&self, prefix: &str) -> Env {
+        self.iter()
+            .filter_map(|(key, value)| Some((key.strip_prefix(prefix)?.to_string(), value.to_string())))
+            .filter(|(key, _)| !key.is_empty())
+            .collect()
+    }
+
+    fn reindex(&mut self) {
+        self.index = self.vars.iter().enumerate().map(|(idx, (key, _))| (key.clone(), idx)).collect();
+    }
+}
+
+impl FromIterator<(String, String)> for Env {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
+        let mut env = Env::new();
+        env.extend(iter);
+        env
+    }
+}
+
+impl Extend<(String, String)> for Env {
+    fn extend<I: IntoIterator<Item = (String, String)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+/// Parsed variables keep their file order
+impl FromIterator<EnvVar> for Env {
+    fn from_iter<I: IntoIterator<Item = EnvVar>>(iter: I) -> Self {
+        iter.into_iter().map(|var| (var.key, var.value)).collect()
+    }
+}
+
+impl From<Vec<EnvVar>> for Env {
+    fn from(vars: Vec<EnvVar>) -> Self {
+        vars.into_iter().collect()
+    }
+}
+
+/// A `HashMap` has no order, so the keys are sorted
+impl From<HashMap<String, String>> for Env {
+    fn from(map: HashMap<String, String>) -> Self {
+        let mut vars: Vec<(String, String)> = map.into_iter().collect();
+        vars.sort();
+        vars.into_iter().collect()
+    }
+}
+
+impl From<Env> for HashMap<String, String> {
+    fn from(env: Env) -> Self {
+        env.vars.into_iter().collect()
+    }
+}
+
+impl IntoIterator for Env {
+    type Item = (String, String);
+    type IntoIter = std::vec::IntoIter<(String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.vars.into_iter()
+    }
+}
+
+/// Lets `Command::envs(&env)` take the variables directly
+impl<'a> IntoIterator for &'a Env {
+    type Item = (&'a str, &'a str);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+/// Borrowing iterator returned by `Env::iter`
+pub struct Iter<'a>(std::slice::Iter<'a, (String, String)>);
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
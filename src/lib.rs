@@ -1,3 +1,14 @@
 // Centralized module declaration
 pub mod parser;
 pub mod banner;
+pub mod trust;
+pub mod load;
+pub mod env;
+#[cfg(feature = "serde")]
+pub mod de;
+
+pub use env::{Change, Env, MergePolicy};
+pub use load::{load, load_from, load_override, LoadReport};
+
+#[cfg(feature = "serde")]
+pub use de::{from_path, from_path_with, from_str, from_str_with, from_vars, FromEnvOptions};
@@ -0,0 +1,82 @@
+//! Applies a .env file to the running process, the library counterpart of `ruster-env load`.
+//!
+//! `load()` and `load_from()` never replace a variable that is already set (the CLI's
+//! `--no-overwrite`); `load_override()` does. Relative paths are looked up in the current
+//! directory first and then in each parent, so a binary started from `src/bin` still finds
+//! the project's `.env`.
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+use crate::parser::{self, Diagnostic, ParseError, ParseOptions};
+
+/// Knobs for `load_with`
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    /// Replace variables that are already set in the process
+    pub overwrite: bool,
+    pub parse: ParseOptions,
+}
+
+/// What `load` did to the process environment
+#[derive(Debug)]
+pub struct LoadReport {
+    /// The file that was read, after the upward search
+    pub path: PathBuf,
+    /// Keys written to the environment, in file order
+    pub set: Vec<String>,
+    /// Keys left alone because they were already set (unless `overwrite`).
+    /// A value containing a NUL byte cannot be stored and is skipped as well.
+    pub skipped: Vec<String>,
+    /// Warnings found while parsing, e.g. unresolved references
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Finds `.env` from the current directory upward and sets its variables,
+/// keeping any that are already set
+pub fn load() -> Result<LoadReport, ParseError> {
+    load_from(".env")
+}
+
+/// Like `load`, for another file name or path
+pub fn load_from<P: AsRef<Path>>(path: P) -> Result<LoadReport, ParseError> {
+    load_with(path, &LoadOptions::default())
+}
+
+/// Like `load_from`, but the file's values replace variables that are already set
+pub fn load_override<P: AsRef<Path>>(path: P) -> Result<LoadReport, ParseError> {
+    load_with(path, &LoadOptions { overwrite: true, ..LoadOptions::default() })
+}
+
+pub fn load_with<P: AsRef<Path>>(path: P, options: &LoadOptions) -> Result<LoadReport, ParseError> {
+    let path = path.as_ref();
+    let found = find(path).ok_or_else(|| ParseError::NotFound { path: path.to_path_buf() })?;
+    let output = parser::parse_env_path_with(&found, &options.parse)?;
+
+    let mut report = LoadReport { path: found, set: Vec::new(), skipped: Vec::new(), diagnostics: output.diagnostics };
+    for var in output.vars {
+        let exists = env::var_os(&var.key).is_some();
+        if (exists && !options.overwrite) || var.value.contains('\0') {
+            report.skipped.push(var.key);
+            continue;
+        }
+        env::set_var(&var.key, &var.value);
+        report.set.push(var.key);
+    }
+    Ok(report)
+}
+
+/// Resolves `path` the way `load_from` does: absolute paths as they are,
+/// relative ones against the current directory and then each of its parents
+pub fn find<P: AsRef<Path>>(path: P) -> Option<PathBuf> {
+    let path = path.as_ref();
+    if path.is_absolute() {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+    find_from(env::current_dir().ok()?, path)
+}
+
+/// Looks for the relative `path` in `dir` and every ancestor of `dir`, nearest first
+pub fn find_from<D: AsRef<Path>, P: AsRef<Path>>(dir: D, path: P) -> Option<PathBuf> {
+    dir.as_ref().ancestors().map(|dir| dir.join(path.as_ref())).find(|candidate| candidate.is_file())
+}
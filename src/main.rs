@@ -1,10 +1,13 @@
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
 use anyhow::{Result, Context};
 use std::io::IsTerminal;
+use std::path::Path;
 use std::process::Command as SysCommand;
+use std::time::Duration;
 
-use ruster_env::parser; 
-use ruster_env::banner;
+use ruster_env::parser;
+use ruster_env::parser::{Dialect, Document, DuplicatePolicy, Encoding, EnvFile, ExecOptions, KeyRule, ParseError, ParseOptions};
+use ruster_env::{banner, trust, Env};
 
 #[derive(Parser)]
 #[command(name = "ruster-env")]
@@ -29,8 +32,8 @@ enum Commands {
     Load {
         /// Path to the .env file
         // 2. Hide automatic default, add it manually to description for compactness
-        #[arg(default_value = ".env", hide_default_value = true, help = "Path to the .env file [default: .env]")]
-        path: String,
+        #[arg(help = "Path to the .env file, or - for stdin [default: .env unless --file is given]")]
+        path: Option<String>,
         
         /// Print verbose output
         #[arg(short, long)]
@@ -40,6 +43,9 @@ enum Commands {
         #[arg(long)]
         no_overwrite: bool,
 
+        #[command(flatten)]
+        parse: ParseArgs,
+
         #[arg(long, value_enum, hide = true)]
         shell: Option<ShellType>,
     },
@@ -48,13 +54,16 @@ enum Commands {
     Unload {
         /// Path to the .env file
         // Tight packing here too
-        #[arg(default_value = ".env", hide_default_value = true, help = "Path to the .env file [default: .env]")]
-        path: String,
+        #[arg(help = "Path to the .env file, or - for stdin [default: .env unless --file is given]")]
+        path: Option<String>,
         
         /// Print verbose output
         #[arg(short, long)]
         verbose: bool,
 
+        #[command(flatten)]
+        parse: ParseArgs,
+
         #[arg(long, value_enum, hide = true)]
         shell: Option<ShellType>,
     },
@@ -71,17 +80,248 @@ enum Commands {
     /// 🏃 Run a command in a clean, isolated environment
     Run {
         /// Path to the .env file
-        #[arg(short, long, default_value = ".env", hide_default_value = true, help = "Path to the .env file [default: .env]")]
-        path: String,
+        #[arg(short, long, help = "Path to the .env file, or - for stdin [default: .env unless --file is given]")]
+        path: Option<String>,
 
         /// Do not overwrite variables that are already set in the system
         #[arg(long)]
         no_overwrite: bool,
 
+        #[command(flatten)]
+        parse: ParseArgs,
+
         /// The command to run
         #[arg(trailing_var_arg = true, allow_hyphen_values = true, required = true)]
         command: Vec<String>,
     },
+
+    /// ✏️  Set a variable in the .env file, keeping comments and layout
+    Set {
+        /// The KEY=VALUE pair to write
+        assignment: String,
+
+        #[arg(short, long, default_value = ".env", hide_default_value = true, help = "Path to the .env file [default: .env]")]
+        file: String,
+
+        /// Also apply the change to the current shell session
+        #[arg(long)]
+        apply: bool,
+
+        #[arg(long, value_enum, hide = true)]
+        shell: Option<ShellType>,
+    },
+
+    /// ✂️  Remove a variable from the .env file, keeping comments and layout
+    Unset {
+        /// The variable key to remove
+        key: String,
+
+        #[arg(short, long, default_value = ".env", hide_default_value = true, help = "Path to the .env file [default: .env]")]
+        file: String,
+
+        /// Also remove the variable from the current shell session
+        #[arg(long)]
+        apply: bool,
+
+        #[arg(long, value_enum, hide = true)]
+        shell: Option<ShellType>,
+    },
+
+    /// 🔐 Allow $(...) commands in a .env file until its content changes
+    Trust {
+        #[arg(default_value = ".env", hide_default_value = true, help = "Path to the .env file [default: .env]")]
+        path: String,
+
+        /// Stop trusting the file
+        #[arg(long)]
+        revoke: bool,
+    },
+}
+
+/// Parser settings shared by every command that reads a .env file
+#[derive(Args)]
+struct ParseArgs {
+    /// Another file to layer on top, left to right (repeatable; later files win)
+    #[arg(short = 'f', long = "file", value_name = "FILE")]
+    files: Vec<String>,
+
+    /// Like --file, but the file may be missing
+    #[arg(long, value_name = "FILE")]
+    optional: Vec<String>,
+
+    /// Layer .env, .env.local, .env.<MODE> and .env.<MODE>.local for the main path (later files win)
+    #[arg(short = 'e', long, env = "RUSTER_ENV", value_name = "MODE")]
+    mode: Option<String>,
+
+    /// Resolve ${VAR} references regardless of the order keys appear in the file
+    #[arg(long)]
+    order_independent: bool,
+
+    /// Treat malformed lines, invalid or duplicate keys and unresolved references as errors
+    #[arg(long)]
+    strict: bool,
+
+    /// Which key names are accepted; others are skipped with a warning
+    #[arg(long, value_enum, default_value_t = KeyRuleArg::Posix)]
+    key_rule: KeyRuleArg,
+
+    /// Which definition wins when a key appears more than once
+    #[arg(long, value_enum, default_value_t = DuplicatesArg::LastWins)]
+    duplicates: DuplicatesArg,
+
+    /// Encoding of the .env file (auto-detects UTF-8, UTF-8 with BOM and UTF-16)
+    #[arg(long, value_enum, default_value_t = EncodingArg::Auto)]
+    encoding: EncodingArg,
+
+    /// Follow another tool's .env rules exactly
+    #[arg(long, value_enum, default_value_t = DialectArg::Native)]
+    dialect: DialectArg,
+
+    /// Run $(...) command substitutions (also enabled for files added with `ruster-env trust`)
+    #[arg(long)]
+    allow_exec: bool,
+
+    /// Shell that runs $(...) commands [default: "cmd /C", or "sh -c" outside Windows]
+    #[arg(long, value_name = "SHELL")]
+    exec_shell: Option<String>,
+
+    /// Seconds a $(...) command may run before it is killed
+    #[arg(long, value_name = "SECONDS", default_value_t = 10)]
+    exec_timeout: u64,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+enum DialectArg {
+    /// ruster-env's own rules (interpolation, escapes, includes)
+    Native,
+    /// docker compose env files
+    Compose,
+    /// python-dotenv
+    Python,
+    /// Node's dotenv package
+    Node,
+    /// Ruby's dotenv gem
+    Ruby,
+    /// systemd EnvironmentFile=
+    Systemd,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+enum EncodingArg {
+    Auto,
+    Utf8,
+    Utf16le,
+    Utf16be,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+enum DuplicatesArg {
+    /// The last definition wins
+    LastWins,
+    /// The first definition wins
+    FirstWins,
+    /// Fail on any duplicate
+    Error,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+enum KeyRuleArg {
+    /// Letters, digits and '_' (not starting with a digit)
+    Posix,
+    /// Also '-', '.' and ':' (e.g. my-key, spring.profile)
+    Relaxed,
+    /// Anything without whitespace, quotes or '='
+    Any,
+}
+
+impl ParseArgs {
+    /// What a command reads: stdin for "-", otherwise the main path (expanded by --mode)
+    /// followed by every --file and --optional in command-line order.
+    /// The main path defaults to .env when no --file or --optional is given, or when a mode is
+    /// set: the mode's cascade is read first and the extra files layer on top of it.
+    /// `matches` are the subcommand's matches, used to interleave --file and --optional.
+    fn input(&self, path: Option<&str>, matches: &ArgMatches) -> Result<EnvInput> {
+        let mut layered: Vec<(usize, EnvFile)> = Vec::new();
+        for (id, optional) in [("files", false), ("optional", true)] {
+            let (Some(values), Some(indices)) = (matches.get_many::<String>(id), matches.indices_of(id)) else {
+                continue;
+            };
+            layered.extend(indices.zip(values).map(|(idx, value)| (idx, EnvFile { path: value.into(), optional })));
+        }
+        layered.sort_by_key(|(idx, _)| *idx);
+
+        if path == Some("-") {
+            if !layered.is_empty() {
+                anyhow::bail!("stdin (-) cannot be combined with --file or --optional");
+            }
+            return Ok(EnvInput::Stdin);
+        }
+        let main = match path {
+            Some(path) => Some(path),
+            None if layered.is_empty() || self.mode.is_some() => Some(".env"),
+            None => None,
+        };
+        let mut files = match (main, &self.mode) {
+            (Some(path), Some(mode)) => parser::mode_files(path, mode),
+            (Some(path), None) => vec![EnvFile::required(path)],
+            (None, _) => Vec::new(),
+        };
+        files.extend(layered.into_iter().map(|(_, file)| file));
+        Ok(EnvInput::Files(files))
+    }
+
+    /// Without --allow-exec, commands still run in each file that is trusted (and only there)
+    fn options(&self) -> ParseOptions {
+        ParseOptions {
+            order_independent: self.order_independent,
+            strict: self.strict,
+            key_rule: match self.key_rule {
+                KeyRuleArg::Posix => KeyRule::Posix,
+                KeyRuleArg::Relaxed => KeyRule::Relaxed,
+                KeyRuleArg::Any => KeyRule::Any,
+            },
+            duplicates: match self.duplicates {
+                DuplicatesArg::LastWins => DuplicatePolicy::LastWins,
+                DuplicatesArg::FirstWins => DuplicatePolicy::FirstWins,
+                DuplicatesArg::Error => DuplicatePolicy::Error,
+            },
+            encoding: match self.encoding {
+                EncodingArg::Auto => None,
+                EncodingArg::Utf8 => Some(Encoding::Utf8),
+                EncodingArg::Utf16le => Some(Encoding::Utf16Le),
+                EncodingArg::Utf16be => Some(Encoding::Utf16Be),
+            },
+            dialect: match self.dialect {
+                DialectArg::Native => Dialect::Native,
+                DialectArg::Compose => Dialect::Compose,
+                DialectArg::Python => Dialect::Python,
+                DialectArg::Node => Dialect::Node,
+                DialectArg::Ruby => Dialect::Ruby,
+                DialectArg::Systemd => Dialect::Systemd,
+            },
+            exec: Some(ExecOptions { trusted_only: !self.allow_exec, ..self.exec_options() }),
+        }
+    }
+
+    fn exec_options(&self) -> ExecOptions {
+        let mut exec = ExecOptions { timeout: Duration::from_secs(self.exec_timeout), ..ExecOptions::default() };
+        if let Some(shell) = &self.exec_shell {
+            // "powershell -NoProfile -Command" -> program plus its leading arguments
+            let mut parts = shell.split_whitespace().map(String::from);
+            if let Some(program) = parts.next() {
+                exec.shell = program;
+                exec.shell_args = parts.collect();
+            }
+        }
+        exec
+    }
+}
+
+/// Where load, unload and run read variables from
+enum EnvInput {
+    Stdin,
+    /// Merged left to right: later files override earlier ones
+    Files(Vec<EnvFile>),
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
@@ -91,14 +331,38 @@ enum ShellType {
 }
 
 fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    if let Err(err) = dispatch(&cli, &matches) {
+        // Parse errors get a rustc-style snippet instead of anyhow's plain chain
+        if let Some(parse_err) = err.downcast_ref::<ParseError>() {
+            report_parse_error(parse_err);
+            std::process::exit(1);
+        }
+        return Err(err);
+    }
+    Ok(())
+}
+
+fn dispatch(cli: &Cli, matches: &ArgMatches) -> Result<()> {
+    // --file and --optional are interleaved by position, which only the raw matches know
+    let sub_matches = matches.subcommand().map(|(_, m)| m).unwrap_or(matches);
     match &cli.command {
         Commands::Init { shell } => handle_init(*shell),
-        Commands::Load { path, shell, verbose, no_overwrite } => handle_load(path, *shell, *verbose, *no_overwrite)?,
+        Commands::Load { path, shell, verbose, no_overwrite, parse } => {
+            handle_load(&parse.input(path.as_deref(), sub_matches)?, *shell, *verbose, *no_overwrite, parse)?
+        }
         // Simplified Unload call (no 'force' or 'safe' args)
-        Commands::Unload { path, shell, verbose } => handle_unload(path, *shell, *verbose)?,
-        Commands::Run { path, command, no_overwrite } => handle_run(path, command, *no_overwrite)?,
+        Commands::Unload { path, shell, verbose, parse } => {
+            handle_unload(&parse.input(path.as_deref(), sub_matches)?, *shell, *verbose, parse)?
+        }
+        Commands::Run { path, command, no_overwrite, parse } => {
+            handle_run(&parse.input(path.as_deref(), sub_matches)?, command, *no_overwrite, parse)?
+        }
         Commands::Show { key } => handle_show( key.clone())?,
+        Commands::Set { assignment, file, apply, shell } => handle_set(assignment, file, *apply, *shell)?,
+        Commands::Unset { key, file, apply, shell } => handle_unset(key, file, *apply, *shell)?,
+        Commands::Trust { path, revoke } => handle_trust(path, *revoke)?,
     }
     Ok(())
 }
@@ -111,10 +375,152 @@ fn detect_shell() -> ShellType {
     }
 }
 
+// --- DIAGNOSTICS ---
+
+/// Parses the input as one merged set and prints its warnings to stderr
+/// (stdout is reserved for the shell script)
+fn read_env(input: &EnvInput, parse: &ParseArgs) -> Result<Env, ParseError> {
+    let output = match input {
+        EnvInput::Stdin => parser::parse_env_stdin_with(&parse.options())?,
+        EnvInput::Files(files) => parser::parse_env_files_with(files, &parse.options())?,
+    };
+    for diag in &output.diagnostics {
+        render_snippet("warning", &diag.message, &diag.path, diag.line, Some((diag.column, &diag.snippet)));
+    }
+    Ok(Env::from(output.vars))
+}
+
+fn report_parse_error(err: &ParseError) {
+    match err {
+        ParseError::Syntax { path, line, column, snippet, message }
+        | ParseError::UnresolvedReference { path, line, column, snippet, message, .. }
+        | ParseError::Include { path, line, column, snippet, message } => {
+            render_snippet("error", message, path, *line, Some((*column, snippet)));
+        }
+        ParseError::DuplicateKey { path, line, column, snippet, key, first_line } => {
+            let message = format!("duplicate key '{}' (first defined on line {})", key, first_line);
+            render_snippet("error", &message, path, *line, Some((*column, snippet)));
+        }
+        ParseError::Command { path, line, column, snippet, command, message } => {
+            let message = format!("command '{}' {}", command, message);
+            render_snippet("error", &message, path, *line, Some((*column, snippet)));
+        }
+        ParseError::Cycle { path, line, chain } => {
+            let message = format!("reference cycle detected: {}", chain.join(" -> "));
+            render_snippet("error", &message, path, *line, None);
+        }
+        ParseError::Strict { diagnostics, .. } => {
+            for diag in diagnostics {
+                render_snippet("error", &diag.message, &diag.path, diag.line, Some((diag.column, &diag.snippet)));
+            }
+            eprintln!("error: aborting due to {} problem(s) (--strict)", diagnostics.len());
+        }
+        ParseError::NotFound { .. } | ParseError::Io { .. } | ParseError::Encoding { .. } => eprintln!("error: {}", err),
+    }
+}
+
+/// Prints a message the way rustc does, with a caret under the offending column:
+///
+/// ```text
+/// warning: line is malformed (missing '='), skipping
+///  --> .env:3:1
+///   |
+/// 3 | SKIP_ME
+///   | ^
+/// ```
+fn render_snippet(level: &str, message: &str, path: &Path, line: usize, snippet: Option<(usize, &str)>) {
+    let gutter = " ".repeat(line.to_string().len());
+    eprintln!("{}: {}", level, message);
+    match snippet {
+        Some((column, text)) => {
+            eprintln!("{}--> {}:{}:{}", gutter, path.display(), line, column);
+            eprintln!("{} |", gutter);
+            eprintln!("{} | {}", line, text);
+            eprintln!("{} | {}^", gutter, " ".repeat(column.saturating_sub(1)));
+        }
+        None => eprintln!("{}--> {}:{}", gutter, path.display(), line),
+    }
+}
+
+// --- SHELL EMISSION ---
+// Everything printed by load/unload is executed by the shell hook, so keys and values
+// must never be able to break out of their quoting.
+
+/// Wraps text in a PowerShell single-quoted literal (no expansion at all).
+/// PowerShell also closes the literal on the typographic quotes U+2018-U+201B, so those
+/// are doubled like `'`.
+fn ps_quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('\'');
+    for c in text.chars() {
+        if matches!(c, '\'' | '\u{2018}'..='\u{201B}') {
+            quoted.push(c);
+        }
+        quoted.push(c);
+    }
+    quoted.push('\'');
+    quoted
+}
+
+/// `$env:KEY` only works for plain identifiers; anything else goes through a literal path
+fn ps_set(key: &str, value: &str) -> String {
+    if KeyRule::Posix.allows(key) {
+        format!("$env:{} = {};", key, ps_quote(value))
+    } else {
+        format!("Set-Item -LiteralPath {} -Value {};", ps_quote(&format!("env:{}", key)), ps_quote(value))
+    }
+}
+
+fn ps_remove(key: &str) -> String {
+    if KeyRule::Posix.allows(key) {
+        format!("Remove-Item env:\\{} -ErrorAction SilentlyContinue;", key)
+    } else {
+        format!("Remove-Item -LiteralPath {} -ErrorAction SilentlyContinue;", ps_quote(&format!("env:{}", key)))
+    }
+}
+
+/// Escapes CMD metacharacters for unquoted text (ECHO lines, caret-style SET)
+fn cmd_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '%' => escaped.push_str("%%"),
+            '^' | '&' | '|' | '<' | '>' | '(' | ')' | '"' => {
+                escaped.push('^');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Builds a SET line for a batch file. Returns None for values CMD cannot hold (line breaks).
+fn cmd_set(key: &str, value: &str) -> Option<String> {
+    if key.contains(['\n', '\r']) || value.contains(['\n', '\r']) {
+        return None;
+    }
+    if key.contains('"') || value.contains('"') {
+        // An inner quote would end the SET "..." form, so escape every metacharacter instead
+        Some(format!("SET {}={}", cmd_escape(key), cmd_escape(value)))
+    } else {
+        // Inside SET "..." only '%' still expands in a batch file
+        Some(format!("SET \"{}={}\"", key.replace('%', "%%"), value.replace('%', "%%")))
+    }
+}
+
+fn cmd_unset(key: &str) -> String {
+    if key.contains('"') {
+        format!("SET {}=", cmd_escape(key))
+    } else {
+        format!("SET \"{}=\"", key.replace('%', "%%"))
+    }
+}
+
 // --- LOGIC HANDLERS ---
 
-fn handle_run(path: &str, args: &[String], no_overwrite: bool) -> Result<()> {
-    let vars = parser::parse_env_file(path)?;
+fn handle_run(input: &EnvInput, args: &[String], no_overwrite: bool, parse: &ParseArgs) -> Result<()> {
+    let mut env = read_env(input, parse)?;
 
     if args.is_empty() {
         anyhow::bail!("No command provided. Usage: ruster-env run -- <command>");
@@ -126,12 +532,11 @@ fn handle_run(path: &str, args: &[String], no_overwrite: bool) -> Result<()> {
     let mut cmd = SysCommand::new(program);
     cmd.args(program_args);
 
-    for var in vars {
-        if no_overwrite && std::env::var(&var.key).is_ok() {
-            continue;
-        }
-        cmd.env(&var.key, &var.value);
+    if no_overwrite {
+        // Asked through std::env::var because Windows variable names are case-insensitive
+        env.retain(|key, _| std::env::var(key).is_err());
     }
+    cmd.envs(&env);
 
     let mut child = cmd.spawn().with_context(|| format!("Failed to spawn command: {}", program))?;
     let status = child.wait()?;
@@ -162,7 +567,7 @@ fn handle_init(shell_arg: Option<ShellType>) {
 function ruster-env {{
     $exe = "{exe_path}"
     $command = $args[0]
-    $eval_commands = @("load", "unload")
+    $eval_commands = @("load", "unload", "set", "unset")
     if ($eval_commands -contains $command) {{
         if ($args -contains "--help" -or $args -contains "-h") {{ & $exe $command --help; return }}
         $code = & $exe $command --shell powershell $args[1..$args.Count]
@@ -181,11 +586,14 @@ REM ruster-env wrapper
 SET "EXE=%~dp0ruster-core.exe"
 IF "%1"=="load" GOTO RunEval
 IF "%1"=="unload" GOTO RunEval
+IF "%1"=="set" GOTO RunEval
+IF "%1"=="unset" GOTO RunEval
 GOTO PassThrough
 :RunEval
     IF "%2"=="--help" GOTO PassThrough
     IF "%2"=="-h" GOTO PassThrough
-    "%EXE%" %1 --shell cmd %2 %3 %4 %5 > "%TEMP%\ruster_tmp.bat"
+    REM %* keeps KEY=VALUE in one piece; %2, %3, ... would split it at "="
+    "%EXE%" %* --shell cmd > "%TEMP%\ruster_tmp.bat"
     CALL "%TEMP%\ruster_tmp.bat"
     DEL "%TEMP%\ruster_tmp.bat"
     EXIT /B 0
@@ -197,39 +605,47 @@ GOTO PassThrough
     }
 }
 
-fn handle_load(path: &str, shell_arg: Option<ShellType>, verbose: bool, no_overwrite: bool) -> Result<()> {
+fn handle_load(input: &EnvInput, shell_arg: Option<ShellType>, verbose: bool, no_overwrite: bool, parse: &ParseArgs) -> Result<()> {
     let shell = shell_arg.unwrap_or_else(detect_shell);
-    let vars = parser::parse_env_file(path)?;
+    let env = read_env(input, parse)?;
 
     match shell {
         ShellType::Powershell => {
-            for var in &vars {
-                if no_overwrite && std::env::var(&var.key).is_ok() {
-                    if verbose { println!("Write-Warning '   [SKIP] {} already exists'; ", var.key); }
+            for (key, value) in &env {
+                if no_overwrite && std::env::var(key).is_ok() {
+                    if verbose { println!("Write-Warning {}; ", ps_quote(&format!("   [SKIP] {} already exists", key))); }
                     continue; 
                 }
-                let safe_val = var.value.replace("'", "''");
-                println!("$env:{} = '{}';", var.key, safe_val);
-                if verbose { println!("Write-Host '   + {}' -ForegroundColor Gray;", var.key); }
+                println!("{}", ps_set(key, value));
+                if verbose { println!("Write-Host {} -ForegroundColor Gray;", ps_quote(&format!("   + {}", key))); }
             }
             if !no_overwrite {
-                println!("Write-Host '[Ruster] Loaded {} variables' -ForegroundColor Green;", vars.len());
+                println!("Write-Host '[Ruster] Loaded {} variables' -ForegroundColor Green;", env.len());
             } else {
                  println!("Write-Host '[Ruster] Loaded variables (Safe Mode)' -ForegroundColor Green;");
             }
         },
         ShellType::Cmd => {
             println!("@echo off");
-            for var in &vars {
-                if no_overwrite && std::env::var(&var.key).is_ok() {
-                    if verbose { println!("ECHO    [SKIP] {} already exists", var.key); }
+            let mut loaded = 0;
+            for (key, value) in &env {
+                if no_overwrite && std::env::var(key).is_ok() {
+                    if verbose { println!("ECHO    [SKIP] {} already exists", cmd_escape(key)); }
                     continue;
                 }
-                println!("SET \"{}={}\"", var.key, var.value);
-                if verbose { println!("ECHO    + {}", var.key); }
+                match cmd_set(key, value) {
+                    Some(line) => println!("{}", line),
+                    None => {
+                        // A line break would end the SET and start a new command
+                        println!("ECHO [Ruster] Skipped {}: multi-line values cannot be set from CMD", cmd_escape(key));
+                        continue;
+                    }
+                }
+                loaded += 1;
+                if verbose { println!("ECHO    + {}", cmd_escape(key)); }
             }
              if !no_overwrite {
-                println!("ECHO [Ruster] Loaded {} variables", vars.len());
+                println!("ECHO [Ruster] Loaded {} variables", loaded);
             } else {
                 println!("ECHO [Ruster] Loaded variables (Safe Mode)");
             }
@@ -237,37 +653,39 @@ fn handle_load(path: &str, shell_arg: Option<ShellType>, verbose: bool, no_overw
     }
     Ok(())
 }
-fn handle_unload(path: &str, shell_arg: Option<ShellType>, verbose: bool) -> Result<()> {
+fn handle_unload(input: &EnvInput, shell_arg: Option<ShellType>, verbose: bool, parse: &ParseArgs) -> Result<()> {
     let shell = shell_arg.unwrap_or_else(detect_shell);
     
     // 1. Parse the file to see what we MIGHT need to unload
-    let vars = match parser::parse_env_file(path) {
+    let env = match read_env(input, parse) {
         Ok(v) => v,
-        Err(_) => {
+        Err(ParseError::NotFound { path }) => {
+            let path = path.display().to_string();
             match shell {
-                ShellType::Powershell => println!("Write-Warning 'Could not find {} to unload variables from.'", path),
-                ShellType::Cmd => println!("ECHO Could not find {} to unload variables from.", path),
+                ShellType::Powershell => println!("Write-Warning {}", ps_quote(&format!("Could not find {} to unload variables from.", path))),
+                ShellType::Cmd => println!("ECHO Could not find {} to unload variables from.", cmd_escape(&path)),
             }
             return Ok(());
         }
+        Err(err) => return Err(err.into()),
     };
 
     let mut count = 0;
 
     match shell {
         ShellType::Powershell => {
-            for var in &vars {
+            for key in env.keys() {
                 // Check if it exists BEFORE counting it
-                let exists = std::env::var(&var.key).is_ok();
+                let exists = std::env::var(key).is_ok();
                 
                 // We always generate the remove command to be safe (idempotent),
                 // but we only count/log it if it was actually there.
-                println!("Remove-Item env:\\{} -ErrorAction SilentlyContinue;", var.key);
+                println!("{}", ps_remove(key));
 
                 if exists {
                     count += 1;
                     if verbose { 
-                        println!("Write-Host '   - {}' -ForegroundColor DarkGray;", var.key); 
+                        println!("Write-Host {} -ForegroundColor DarkGray;", ps_quote(&format!("   - {}", key)));
                     }
                 }
             }
@@ -280,15 +698,15 @@ fn handle_unload(path: &str, shell_arg: Option<ShellType>, verbose: bool) -> Res
         },
         ShellType::Cmd => {
             println!("@echo off");
-            for var in &vars {
-                let exists = std::env::var(&var.key).is_ok();
+            for key in env.keys() {
+                let exists = std::env::var(key).is_ok();
 
-                println!("SET \"{}=\"", var.key);
+                println!("{}", cmd_unset(key));
 
                 if exists {
                     count += 1;
                     if verbose { 
-                        println!("ECHO    - {}", var.key); 
+                        println!("ECHO    - {}", cmd_escape(key));
                     }
                 }
             }
@@ -303,6 +721,87 @@ fn handle_unload(path: &str, shell_arg: Option<ShellType>, verbose: bool) -> Res
     Ok(())
 }
 
+fn handle_set(assignment: &str, file: &str, apply: bool, shell_arg: Option<ShellType>) -> Result<()> {
+    let Some((key, value)) = assignment.split_once('=') else {
+        anyhow::bail!("Expected KEY=VALUE, got '{}'", assignment);
+    };
+    let key = key.trim();
+    if !KeyRule::Posix.allows(key) {
+        anyhow::bail!("'{}' is not a valid variable name (letters, digits and '_', not starting with a digit)", key);
+    }
+
+    // A missing file is fine: `set` creates it
+    let mut doc = match Document::from_path(file) {
+        Ok(doc) => doc,
+        Err(ParseError::NotFound { .. }) => Document::default(),
+        Err(err) => return Err(err.into()),
+    };
+    doc.set(key, value);
+    doc.save(file).with_context(|| format!("Failed to write {}", file))?;
+
+    let message = format!("[Ruster] Set {} in {}", key, file);
+    match (shell_arg, apply) {
+        (None, false) => println!("{}", message),
+        _ => match shell_arg.unwrap_or_else(detect_shell) {
+            ShellType::Powershell => {
+                if apply { println!("{}", ps_set(key, value)); }
+                println!("Write-Host {} -ForegroundColor Green;", ps_quote(&message));
+            }
+            ShellType::Cmd => {
+                println!("@echo off");
+                if apply {
+                    match cmd_set(key, value) {
+                        Some(line) => println!("{}", line),
+                        None => println!("ECHO [Ruster] Skipped {}: multi-line values cannot be set from CMD", cmd_escape(key)),
+                    }
+                }
+                println!("ECHO {}", cmd_escape(&message));
+            }
+        },
+    }
+    Ok(())
+}
+
+fn handle_unset(key: &str, file: &str, apply: bool, shell_arg: Option<ShellType>) -> Result<()> {
+    let mut doc = Document::from_path(file)?;
+    let message = if doc.remove(key) {
+        doc.save(file).with_context(|| format!("Failed to write {}", file))?;
+        format!("[Ruster] Removed {} from {}", key, file)
+    } else {
+        format!("[Ruster] {} is not defined in {}", key, file)
+    };
+
+    match (shell_arg, apply) {
+        (None, false) => println!("{}", message),
+        _ => match shell_arg.unwrap_or_else(detect_shell) {
+            ShellType::Powershell => {
+                if apply { println!("{}", ps_remove(key)); }
+                println!("Write-Host {} -ForegroundColor Yellow;", ps_quote(&message));
+            }
+            ShellType::Cmd => {
+                println!("@echo off");
+                if apply { println!("{}", cmd_unset(key)); }
+                println!("ECHO {}", cmd_escape(&message));
+            }
+        },
+    }
+    Ok(())
+}
+
+fn handle_trust(path: &str, revoke: bool) -> Result<()> {
+    if revoke {
+        if trust::revoke(path).with_context(|| format!("Failed to update the trust store for {}", path))? {
+            println!("🔒 {} is no longer trusted", path);
+        } else {
+            println!("{} was not trusted", path);
+        }
+    } else {
+        trust::trust(path).with_context(|| format!("Failed to trust {}", path))?;
+        println!("🔓 Trusted {}: its $(...) commands will run until the file changes", path);
+    }
+    Ok(())
+}
+
 fn handle_show(key: Option<String>) -> Result<()> {
     if let Some(target_key) = key {
         // --- Single Variable Mode ---
@@ -0,0 +1,161 @@
+/// Whose .env rules to follow. Each tool disagrees a little about quotes, escapes,
+/// comments and interpolation; picking the tool that reads the file in production
+/// makes ruster-env load exactly the same values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    /// ruster-env's own superset: POSIX interpolation, escapes, `$(...)` and includes
+    #[default]
+    Native,
+    /// docker compose `env_file` / `.env`: POSIX interpolation without assignment (`:=`)
+    Compose,
+    /// python-dotenv: only `${VAR}` and `${VAR:-default}`, Python-style escapes
+    Python,
+    /// Node's `dotenv` package: no interpolation, `\n` and `\r` only, backtick quotes, `#` always starts a comment
+    Node,
+    /// Ruby's `dotenv` gem: `$VAR`, `${VAR}` and `$(command)`, no defaults
+    Ruby,
+    /// systemd `EnvironmentFile=`: no interpolation or inline comments, `;` comments, `\` line continuation
+    Systemd,
+}
+
+/// What a '#' inside an unquoted value means
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum InlineComments {
+    /// Starts a comment when preceded by whitespace ("a #b"), kept in "a#b"
+    AfterWhitespace,
+    /// Always starts a comment
+    Anywhere,
+    /// Never: the '#' is part of the value
+    Never,
+}
+
+/// Which backslash sequences are decoded in double-quoted values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Escapes {
+    /// `\n \r \t \" \\ \$ \u{XXXX}`, anything else kept verbatim
+    Native,
+    /// Python string escapes: `\n \r \t \a \b \f \v \" \' \\`
+    Python,
+    /// Only `\n` and `\r`; every other backslash stays
+    Newlines,
+    /// `\n` and `\r`, then any other `\X` becomes `X` (`\$` still blocks interpolation)
+    Ruby,
+    /// Shell-style: `\" \\ \$` and backtick lose the backslash, anything else is kept
+    Systemd,
+}
+
+/// Which `$` forms are expanded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Interpolation {
+    None,
+    /// `${VAR}` and `${VAR:-default}` only
+    Braced,
+    /// `$VAR` and `${VAR}` without operators
+    Simple,
+    /// `$VAR`, `${VAR}` and the operators `- ? +` (with or without ':'), plus `$$`
+    Posix,
+    /// Posix plus the assigning `=` / `:=`
+    Full,
+}
+
+/// The syntax switches behind a `Dialect`
+#[derive(Debug)]
+pub(super) struct Rules {
+    /// "export KEY=value" is accepted
+    pub(super) export: bool,
+    /// "# @include" and "source" directives are followed
+    pub(super) includes: bool,
+    /// A line whose first character is one of these is a comment
+    pub(super) comment_chars: &'static [char],
+    pub(super) inline_comments: InlineComments,
+    /// `...` is a third, literal quote style
+    pub(super) backticks: bool,
+    /// `\'` and `\\` are decoded inside single quotes
+    pub(super) single_escapes: bool,
+    /// A backslash in an unquoted value escapes the next character; at the end of a line it continues the value
+    pub(super) unquoted_escapes: bool,
+    pub(super) escapes: Escapes,
+    pub(super) interpolation: Interpolation,
+    /// `$(command)` is recognised (it still only runs when enabled)
+    pub(super) commands: bool,
+    /// An unresolved reference stays in the value as written; otherwise it becomes empty
+    pub(super) keep_unresolved: bool,
+}
+
+const NATIVE: Rules = Rules {
+    export: true,
+    includes: true,
+    comment_chars: &['#'],
+    inline_comments: InlineComments::AfterWhitespace,
+    backticks: false,
+    single_escapes: false,
+    unquoted_escapes: false,
+    escapes: Escapes::Native,
+    interpolation: Interpolation::Full,
+    commands: true,
+    keep_unresolved: true,
+};
+
+const COMPOSE: Rules = Rules {
+    includes: false,
+    interpolation: Interpolation::Posix,
+    commands: false,
+    keep_unresolved: false,
+    ..NATIVE
+};
+
+const PYTHON: Rules = Rules {
+    includes: false,
+    single_escapes: true,
+    escapes: Escapes::Python,
+    interpolation: Interpolation::Braced,
+    commands: false,
+    keep_unresolved: false,
+    ..NATIVE
+};
+
+const NODE: Rules = Rules {
+    includes: false,
+    inline_comments: InlineComments::Anywhere,
+    backticks: true,
+    escapes: Escapes::Newlines,
+    interpolation: Interpolation::None,
+    commands: false,
+    keep_unresolved: false,
+    ..NATIVE
+};
+
+const RUBY: Rules = Rules {
+    includes: false,
+    escapes: Escapes::Ruby,
+    interpolation: Interpolation::Simple,
+    keep_unresolved: false,
+    ..NATIVE
+};
+
+const SYSTEMD: Rules = Rules {
+    export: false,
+    includes: false,
+    comment_chars: &['#', ';'],
+    inline_comments: InlineComments::Never,
+    backticks: false,
+    single_escapes: false,
+    unquoted_escapes: true,
+    escapes: Escapes::Systemd,
+    interpolation: Interpolation::None,
+    commands: false,
+    keep_unresolved: false,
+};
+
+impl Dialect {
+    pub(super) fn rules(self) -> &'static Rules {
+        match self {
+            Dialect::Native => &NATIVE,
+            Dialect::Compose => &COMPOSE,
+            Dialect::Python => &PYTHON,
+            Dialect::Node => &NODE,
+            Dialect::Ruby => &RUBY,
+            Dialect::Systemd => &SYSTEMD,
+        }
+    }
+}
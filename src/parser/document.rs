@@ -0,0 +1,359 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use super::error::ParseError;
+use super::encoding::Encoding;
+use super::{
+    column_of, decode_bytes, find_closing_quote, open_quote, read_file, strip_inline_comment, strip_quotes,
+    text_after_quote, Dialect, InlineComments, QuoteStyle, STRING_SOURCE,
+};
+
+/// A lossless view of a .env file: comments, blank lines, `export` prefixes, quoting,
+/// spacing and key order are all kept, and `to_string()` gives back the exact input.
+///
+/// Values are kept as written (no escapes decoded, no interpolation); use
+/// `parse_env_file` for the resolved variables.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Document {
+    pub items: Vec<Item>,
+    /// Encoding the file was read in; `save` writes it back the same way
+    pub encoding: Encoding,
+    /// Whether the file started with a byte order mark; `to_string()` writes it back as U+FEFF
+    pub bom: bool,
+}
+
+/// One logical line of the document (a multiline quoted value counts as one item)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    pub kind: ItemKind,
+    /// "\n", "\r\n", or "" for a last line without a line break
+    pub newline: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ItemKind {
+    /// Empty or whitespace-only line, kept verbatim
+    Blank(String),
+    /// Full-line comment, including its leading whitespace and '#'
+    Comment(String),
+    Assignment(Assignment),
+    /// A line without '=' that the parser skips as malformed, kept verbatim
+    Invalid(String),
+}
+
+/// `KEY=value` split into every piece of its original text:
+/// `{leading}{export}{key}{before_eq}={after_eq}{raw_value}{trailing}`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assignment {
+    pub leading: String,
+    /// "export " plus any following whitespace, or empty
+    pub export: String,
+    pub key: String,
+    pub before_eq: String,
+    pub after_eq: String,
+    /// The value as written, quotes included. May contain line breaks when quoted.
+    pub raw_value: String,
+    pub quote: QuoteStyle,
+    /// Whitespace and inline comment after the value
+    pub trailing: String,
+    /// 1-based line the assignment starts on (as parsed; not updated by edits)
+    pub line: usize,
+}
+
+impl Document {
+    /// Parses .env text. Errors name the source `<string>`.
+    pub fn parse(content: &str) -> Result<Document, ParseError> {
+        let mut doc = parse_document(Path::new(STRING_SOURCE), content.strip_prefix('\u{feff}').unwrap_or(content))?;
+        doc.bom = content.starts_with('\u{feff}');
+        Ok(doc)
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Document, ParseError> {
+        let path = path.as_ref();
+        let bytes = read_file(path)?;
+        let decoded = decode_bytes(path, bytes, None)?;
+        let mut doc = parse_document(path, &decoded.text)?;
+        doc.encoding = decoded.encoding;
+        doc.bom = decoded.bom;
+        Ok(doc)
+    }
+
+    pub fn assignments(&self) -> impl Iterator<Item = &Assignment> {
+        self.items.iter().filter_map(|item| match &item.kind {
+            ItemKind::Assignment(assignment) => Some(assignment),
+            _ => None,
+        })
+    }
+
+    /// The last assignment of `key` (the one that wins when the file is loaded)
+    pub fn get(&self, key: &str) -> Option<&Assignment> {
+        self.assignments().filter(|a| a.key == key).last()
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Assignment> {
+        self.items
+            .iter_mut()
+            .filter_map(|item| match &mut item.kind {
+                ItemKind::Assignment(assignment) if assignment.key == key => Some(assignment),
+                _ => None,
+            })
+            .last()
+    }
+}
+
+impl Document {
+    /// Sets `key` to the literal `value`, picking the quoting that keeps it intact.
+    /// An existing key is updated in place (its comment and spacing survive);
+    /// a new key is appended at the end.
+    pub fn set(&mut self, key: &str, value: &str) {
+        let (raw_value, quote) = quote_value(value);
+
+        if let Some(assignment) = self.get_mut(key) {
+            assignment.raw_value = raw_value;
+            assignment.quote = quote;
+            return;
+        }
+
+        let newline = self.newline_style();
+        if let Some(last) = self.items.last_mut() {
+            if last.newline.is_empty() {
+                last.newline = newline.to_string();
+            }
+        }
+        let line = self.items.len() + 1;
+        self.items.push(Item {
+            kind: ItemKind::Assignment(Assignment {
+                leading: String::new(),
+                export: String::new(),
+                key: key.to_string(),
+                before_eq: String::new(),
+                after_eq: String::new(),
+                raw_value,
+                quote,
+                trailing: String::new(),
+                line,
+            }),
+            newline: newline.to_string(),
+        });
+    }
+
+    /// Removes every assignment of `key`. Comments and other lines are left alone.
+    /// Returns false if the key was not present.
+    pub fn remove(&mut self, key: &str) -> bool {
+        let before = self.items.len();
+        self.items
+            .retain(|item| !matches!(&item.kind, ItemKind::Assignment(a) if a.key == key));
+        self.items.len() != before
+    }
+
+    /// Writes the document to `path` atomically: a temp file next to it is renamed over
+    /// the target, so readers never see a half-written file. A symlinked `path` is followed,
+    /// so its target is updated, and the existing file's permissions are kept.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = match fs::canonicalize(path.as_ref()) {
+            Ok(target) => target,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => path.as_ref().to_path_buf(),
+            Err(err) => return Err(err),
+        };
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+        let mut tmp_name = std::ffi::OsString::from(".");
+        tmp_name.push(file_name);
+        tmp_name.push(".ruster-tmp");
+        let tmp_path = path.with_file_name(tmp_name);
+        let permissions = fs::metadata(&path).ok().map(|metadata| metadata.permissions());
+
+        let result = fs::File::create(&tmp_path).and_then(|mut file| {
+            if let Some(permissions) = permissions {
+                file.set_permissions(permissions)?;
+            }
+            file.write_all(&self.encoding.encode(&self.to_string()))?;
+            file.sync_all()
+        });
+        match result.and_then(|_| fs::rename(&tmp_path, &path)) {
+            Ok(()) => Ok(()),
+            Err(err) => {
+                let _ = fs::remove_file(&tmp_path);
+                Err(err)
+            }
+        }
+    }
+
+    /// "\r\n" if the document already uses it, "\n" otherwise
+    fn newline_style(&self) -> &'static str {
+        match self.items.iter().find(|item| !item.newline.is_empty()) {
+            Some(item) if item.newline == "\r\n" => "\r\n",
+            _ => "\n",
+        }
+    }
+}
+
+/// Picks the simplest quoting that reads back as exactly `value`:
+/// bare when nothing is special, single quotes when possible (fully literal),
+/// double quotes with escapes otherwise.
+pub fn quote_value(value: &str) -> (String, QuoteStyle) {
+    let is_plain = value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "_-.,:/@+%=~*".contains(c));
+    if is_plain {
+        return (value.to_string(), QuoteStyle::None);
+    }
+
+    if !value.contains(['\'', '\n', '\r']) {
+        return (format!("'{}'", value), QuoteStyle::Single);
+    }
+
+    let mut raw = String::with_capacity(value.len() + 2);
+    raw.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => raw.push_str("\\\""),
+            '\\' => raw.push_str("\\\\"),
+            '$' => raw.push_str("\\$"),
+            '\n' => raw.push_str("\\n"),
+            '\r' => raw.push_str("\\r"),
+            '\t' => raw.push_str("\\t"),
+            _ => raw.push(c),
+        }
+    }
+    raw.push('"');
+    (raw, QuoteStyle::Double)
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.bom {
+            f.write_str("\u{feff}")?;
+        }
+        for item in &self.items {
+            write!(f, "{}{}", item.kind, item.newline)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ItemKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ItemKind::Blank(text) | ItemKind::Comment(text) | ItemKind::Invalid(text) => f.write_str(text),
+            ItemKind::Assignment(assignment) => assignment.fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for Assignment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}{}{}={}{}{}",
+            self.leading, self.export, self.key, self.before_eq, self.after_eq, self.raw_value, self.trailing
+        )
+    }
+}
+
+fn parse_document(path: &Path, content: &str) -> Result<Document, ParseError> {
+    // Documents are edited in ruster-env's own syntax
+    let rules = Dialect::Native.rules();
+    // Keep every line ending so the document can be written back unchanged
+    let lines: Vec<(&str, &str)> = content
+        .split_inclusive('\n')
+        .map(|raw| match raw.strip_suffix("\r\n").or_else(|| raw.strip_suffix('\n')) {
+            Some(text) => (text, &raw[text.len()..]),
+            None => (raw, ""),
+        })
+        .collect();
+
+    let mut items = Vec::new();
+    let mut line_idx = 0;
+    while line_idx < lines.len() {
+        let line_num = line_idx + 1;
+        let (text, mut newline) = lines[line_idx];
+        line_idx += 1;
+
+        let trimmed = text.trim();
+        let kind = if trimmed.is_empty() {
+            ItemKind::Blank(text.to_string())
+        } else if trimmed.starts_with('#') {
+            ItemKind::Comment(text.to_string())
+        } else {
+            let rest = text.trim_start();
+            let leading = &text[..text.len() - rest.len()];
+
+            let export_len = match rest.strip_prefix("export ") {
+                Some(after) => rest.len() - after.trim_start().len(),
+                None => 0,
+            };
+            let (export, rest) = rest.split_at(export_len);
+
+            match rest.split_once('=') {
+                None => ItemKind::Invalid(text.to_string()),
+                Some((key_part, value_part)) => {
+                    let key = key_part.trim_end();
+                    let value_start = value_part.trim_start();
+                    let after_eq = &value_part[..value_part.len() - value_start.len()];
+                    let mut value_text = value_start.to_string();
+
+                    // Same multiline rule as the parser: keep reading until the quote closes
+                    if let Some(quote) = open_quote(value_text.trim_end(), rules) {
+                        loop {
+                            if line_idx >= lines.len() {
+                                return Err(ParseError::Syntax {
+                                    path: path.to_path_buf(),
+                                    line: line_num,
+                                    column: column_of(text, value_start),
+                                    snippet: text.to_string(),
+                                    message: format!("unterminated quote in value of '{}'", key),
+                                });
+                            }
+                            value_text.push_str(newline);
+                            value_text.push_str(lines[line_idx].0);
+                            newline = lines[line_idx].1;
+                            line_idx += 1;
+                            if find_closing_quote(&value_text[1..], quote, rules).is_some() {
+                                break;
+                            }
+                        }
+                    }
+                    if let Some(junk) = text_after_quote(value_text.trim_end(), rules) {
+                        let closing = lines[line_idx - 1].0;
+                        return Err(ParseError::Syntax {
+                            path: path.to_path_buf(),
+                            line: line_idx,
+                            column: column_of(closing, &closing[closing.trim_end().len() - junk.len()..]),
+                            snippet: closing.to_string(),
+                            message: format!("unexpected text after the closing quote of '{}'", key),
+                        });
+                    }
+
+                    let (quote, raw_len) = match strip_quotes(value_text.trim_end(), rules) {
+                        (QuoteStyle::None, _) => {
+                            let value = strip_inline_comment(&value_text, InlineComments::AfterWhitespace);
+                            (QuoteStyle::None, value.trim_end().len())
+                        }
+                        (style, body) => (style, body.len() + 2),
+                    };
+                    let (raw_value, trailing) = value_text.split_at(raw_len);
+
+                    ItemKind::Assignment(Assignment {
+                        leading: leading.to_string(),
+                        export: export.to_string(),
+                        key: key.to_string(),
+                        before_eq: key_part[key.len()..].to_string(),
+                        after_eq: after_eq.to_string(),
+                        raw_value: raw_value.to_string(),
+                        quote,
+                        trailing: trailing.to_string(),
+                        line: line_num,
+                    })
+                }
+            }
+        };
+
+        items.push(Item { kind, newline: newline.to_string() });
+    }
+
+    Ok(Document { items, ..Default::default() })
+}
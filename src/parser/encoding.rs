@@ -0,0 +1,88 @@
+/// Text encodings a .env file may be saved in. Notepad and PowerShell's `Out-File`
+/// commonly produce UTF-16LE or UTF-8 with a byte order mark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// Text decoded from raw bytes, with enough detail to write it back the same way
+pub(super) struct Decoded {
+    pub text: String,
+    pub encoding: Encoding,
+    pub bom: bool,
+}
+
+impl Encoding {
+    /// Guesses the encoding: a BOM wins; otherwise a NUL byte next to an ASCII byte
+    /// at the start means UTF-16 without BOM; everything else is treated as UTF-8.
+    pub fn detect(bytes: &[u8]) -> Encoding {
+        if bytes.starts_with(UTF8_BOM) {
+            Encoding::Utf8
+        } else if bytes.starts_with(UTF16LE_BOM) {
+            Encoding::Utf16Le
+        } else if bytes.starts_with(UTF16BE_BOM) {
+            Encoding::Utf16Be
+        } else {
+            match bytes {
+                [lo, 0, ..] if *lo != 0 => Encoding::Utf16Le,
+                [0, hi, ..] if *hi != 0 => Encoding::Utf16Be,
+                _ => Encoding::Utf8,
+            }
+        }
+    }
+
+    fn bom(self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 => UTF8_BOM,
+            Encoding::Utf16Le => UTF16LE_BOM,
+            Encoding::Utf16Be => UTF16BE_BOM,
+        }
+    }
+
+    /// Encodes text as is; a leading U+FEFF becomes this encoding's BOM.
+    pub(super) fn encode(self, text: &str) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(text.len());
+        match self {
+            Encoding::Utf8 => bytes.extend_from_slice(text.as_bytes()),
+            Encoding::Utf16Le => text.encode_utf16().for_each(|unit| bytes.extend_from_slice(&unit.to_le_bytes())),
+            Encoding::Utf16Be => text.encode_utf16().for_each(|unit| bytes.extend_from_slice(&unit.to_be_bytes())),
+        }
+        bytes
+    }
+}
+
+/// Decodes file contents. `forced` skips detection (a matching BOM is still stripped).
+/// UTF-8 text takes over the buffer instead of copying it.
+pub(super) fn decode(mut bytes: Vec<u8>, forced: Option<Encoding>) -> Result<Decoded, String> {
+    let encoding = forced.unwrap_or_else(|| Encoding::detect(&bytes));
+    let bom = bytes.starts_with(encoding.bom());
+    if bom {
+        bytes.drain(..encoding.bom().len());
+    }
+
+    let text = match encoding {
+        Encoding::Utf8 => String::from_utf8(bytes)
+            .map_err(|e| format!("file is not valid UTF-8 (byte {}); try --encoding", e.utf8_error().valid_up_to()))?,
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            if !bytes.len().is_multiple_of(2) {
+                return Err("file has an odd number of bytes for UTF-16".to_string());
+            }
+            let units = bytes.chunks_exact(2).map(|pair| match encoding {
+                Encoding::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                _ => u16::from_be_bytes([pair[0], pair[1]]),
+            });
+            char::decode_utf16(units)
+                .collect::<Result<String, _>>()
+                .map_err(|e| format!("file is not valid UTF-16 (unpaired surrogate {:#06x})", e.unpaired_surrogate()))?
+        }
+    };
+
+    Ok(Decoded { text, encoding, bom })
+}
@@ -0,0 +1,177 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Everything that can stop `parse_env_file` from producing variables
+#[derive(Debug)]
+pub enum ParseError {
+    /// The .env file does not exist
+    NotFound { path: PathBuf },
+    /// The file exists but could not be read
+    Io { path: PathBuf, source: io::Error },
+    /// The bytes could not be decoded (e.g. invalid UTF-8 without a BOM)
+    Encoding { path: PathBuf, message: String },
+    /// The file is not valid .env syntax (e.g. an unterminated quote)
+    Syntax {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        snippet: String,
+        message: String,
+    },
+    /// A reference that had to resolve did not, e.g. `${DB_HOST:?DB_HOST must be set}`
+    UnresolvedReference {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        snippet: String,
+        name: String,
+        message: String,
+    },
+    /// A `$(command)` substitution failed, exited non-zero or timed out
+    Command {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        snippet: String,
+        command: String,
+        message: String,
+    },
+    /// An include directive points at a missing file or back at a file being included
+    Include {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        snippet: String,
+        message: String,
+    },
+    /// Variables that reference each other in a loop. `chain` starts and ends with the same key.
+    Cycle {
+        path: PathBuf,
+        line: usize,
+        chain: Vec<String>,
+    },
+    /// A key was defined twice under `DuplicatePolicy::Error`
+    DuplicateKey {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        snippet: String,
+        key: String,
+        first_line: usize,
+    },
+    /// Strict mode found warnings; every one of them is listed
+    Strict {
+        path: PathBuf,
+        diagnostics: Vec<Diagnostic>,
+    },
+}
+
+impl ParseError {
+    /// The file the error belongs to
+    pub fn path(&self) -> &PathBuf {
+        match self {
+            ParseError::NotFound { path }
+            | ParseError::Io { path, .. }
+            | ParseError::Encoding { path, .. }
+            | ParseError::Syntax { path, .. }
+            | ParseError::UnresolvedReference { path, .. }
+            | ParseError::Command { path, .. }
+            | ParseError::Include { path, .. }
+            | ParseError::Cycle { path, .. }
+            | ParseError::DuplicateKey { path, .. }
+            | ParseError::Strict { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::NotFound { path } => write!(f, "File not found: {}", path.display()),
+            ParseError::Io { path, .. } => write!(f, "Failed to open .env file: {}", path.display()),
+            ParseError::Encoding { path, message } => write!(f, "{}: {}", path.display(), message),
+            ParseError::Syntax { path, line, column, message, .. }
+            | ParseError::UnresolvedReference { path, line, column, message, .. }
+            | ParseError::Include { path, line, column, message, .. } => {
+                write!(f, "{}:{}:{}: {}", path.display(), line, column, message)
+            }
+            ParseError::Command { path, line, column, command, message, .. } => write!(
+                f,
+                "{}:{}:{}: command '{}' {}",
+                path.display(),
+                line,
+                column,
+                command,
+                message
+            ),
+            ParseError::Cycle { path, line, chain } => write!(
+                f,
+                "{}:{}: reference cycle detected: {}",
+                path.display(),
+                line,
+                chain.join(" -> ")
+            ),
+            ParseError::DuplicateKey { path, line, column, key, first_line, .. } => write!(
+                f,
+                "{}:{}:{}: duplicate key '{}' (first defined on line {})",
+                path.display(),
+                line,
+                column,
+                key,
+                first_line
+            ),
+            ParseError::Strict { path, diagnostics } => {
+                write!(f, "{}: {} problem(s) found in strict mode", path.display(), diagnostics.len())?;
+                if let Some(first) = diagnostics.first() {
+                    write!(f, "; first: {}", first)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// What a non-fatal `Diagnostic` is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// A line that is not a comment and has no '=' (it is skipped)
+    Malformed,
+    /// A `${VAR}` or `$VAR` that matched neither the file nor the system (kept literally)
+    UnresolvedReference,
+    /// A key rejected by `ParseOptions::key_rule` (the entry is skipped)
+    InvalidKey,
+    /// A key defined more than once
+    DuplicateKey,
+    /// A `$(command)` kept literally because command execution is disabled
+    CommandSubstitution,
+}
+
+/// A warning about the file. Parsing continues; the caller decides how to show it.
+/// With `ParseOptions::strict` they become a `ParseError::Strict` instead.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub path: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub snippet: String,
+    pub message: String,
+    /// Another line involved, e.g. the first definition of a duplicate key
+    pub related_line: Option<usize>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}: {}", self.path.display(), self.line, self.column, self.message)
+    }
+}
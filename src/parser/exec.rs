@@ -0,0 +1,121 @@
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How `$(...)` command substitutions are run once they are enabled
+/// through `ParseOptions::exec`. They are off by default because `load`
+/// output is evaluated in an interactive shell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecOptions {
+    /// Program that receives the command text, e.g. `cmd` or `sh`
+    pub shell: String,
+    /// Arguments placed before the command text, e.g. `/C` or `-c`
+    pub shell_args: Vec<String>,
+    /// The command is killed and reported as failed after this long
+    pub timeout: Duration,
+    /// Only run commands from files `trust::is_trusted` accepts, included files too.
+    /// Set when execution comes from trusting a file rather than an explicit opt-in.
+    pub trusted_only: bool,
+}
+
+impl Default for ExecOptions {
+    fn default() -> Self {
+        let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+        ExecOptions {
+            shell: shell.to_string(),
+            shell_args: vec![flag.to_string()],
+            timeout: Duration::from_secs(10),
+            trusted_only: false,
+        }
+    }
+}
+
+/// Adds the command as the shell's last argument. cmd.exe reads its command line as
+/// written instead of splitting it the way `arg` quotes it, so it gets the text untouched.
+#[cfg(windows)]
+fn push_command(shell: &mut Command, command: &str, program: &str) {
+    use std::os::windows::process::CommandExt;
+
+    let is_cmd = std::path::Path::new(program)
+        .file_stem()
+        .is_some_and(|stem| stem.eq_ignore_ascii_case("cmd"));
+    if is_cmd {
+        shell.raw_arg(command);
+    } else {
+        shell.arg(command);
+    }
+}
+
+#[cfg(not(windows))]
+fn push_command(shell: &mut Command, command: &str, _program: &str) {
+    shell.arg(command);
+}
+
+/// Runs `command` through the configured shell and returns its stdout without
+/// trailing line breaks, like `$(...)` in a POSIX shell.
+pub(super) fn run(command: &str, options: &ExecOptions) -> Result<String, String> {
+    let mut shell = Command::new(&options.shell);
+    shell.args(&options.shell_args);
+    push_command(&mut shell, command, &options.shell);
+    let mut child = shell
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to start '{}': {}", options.shell, e))?;
+
+    // Drain the pipes on threads so a chatty command cannot block on a full pipe
+    let out_reader = drain(child.stdout.take().expect("stdout is piped"));
+    let err_reader = drain(child.stderr.take().expect("stderr is piped"));
+
+    let deadline = Instant::now() + options.timeout;
+    let status = loop {
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) => break status,
+            None if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("timed out after {:?}", options.timeout));
+            }
+            None => thread::sleep(Duration::from_millis(10)),
+        }
+    };
+
+    // A background process ("sleep 60 &") keeps the pipes open after the shell exits,
+    // so waiting for the rest of the output is bounded by the same deadline
+    let collect = |reader: mpsc::Receiver<Vec<u8>>| {
+        match reader.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(buf) => Ok(buf),
+            Err(RecvTimeoutError::Timeout) => Err(format!("timed out after {:?}", options.timeout)),
+            Err(RecvTimeoutError::Disconnected) => Ok(Vec::new()),
+        }
+    };
+    let out = collect(out_reader)?;
+    let err = collect(err_reader)?;
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&err);
+        let detail = stderr.trim();
+        return Err(match status.code() {
+            Some(code) if detail.is_empty() => format!("exited with status {}", code),
+            Some(code) => format!("exited with status {}: {}", code, detail),
+            None => "was terminated by a signal".to_string(),
+        });
+    }
+
+    let mut output = String::from_utf8(out).map_err(|_| "printed output that is not valid UTF-8".to_string())?;
+    output.truncate(output.trim_end_matches(['\r', '\n']).len());
+    Ok(output)
+}
+
+/// Reads a pipe to the end on its own thread and sends the bytes once it closes
+fn drain<R: Read + Send + 'static>(mut pipe: R) -> mpsc::Receiver<Vec<u8>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = pipe.read_to_end(&mut buf);
+        let _ = sender.send(buf);
+    });
+    receiver
+}
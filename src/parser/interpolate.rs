@@ -0,0 +1,621 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use super::error::{Diagnostic, DiagnosticKind, ParseError};
+use super::dialect::{Escapes, Interpolation, Rules};
+use super::exec::{self, ExecOptions};
+use super::{ParseOptions, QuoteStyle, RawEntry, Reference, ReferenceSource};
+
+/// Where `interpolate` looks up referenced variables
+pub(super) trait Scope {
+    /// The value of `name` and where it was found
+    fn lookup(&mut self, name: &str) -> Result<Option<(String, ReferenceSource)>, ParseError>;
+    /// Records a `${VAR:=default}` assignment
+    fn assign(&mut self, name: &str, value: String);
+}
+
+fn system(name: &str) -> Option<(String, ReferenceSource)> {
+    std::env::var(name).ok().map(|value| (value, ReferenceSource::System))
+}
+
+fn file_source(entry: &RawEntry<'_>) -> ReferenceSource {
+    ReferenceSource::File { path: entry.source.path.clone(), line: entry.line }
+}
+
+/// What a name is bound to while resolving sequentially
+enum Binding {
+    /// The latest definition so far: an index into the entries
+    Entry(usize),
+    /// A `${VAR:=default}` assignment
+    Assigned(String),
+}
+
+/// Sequential mode: only variables defined earlier in the file, then the system environment.
+/// Keys and values are borrowed from the entries, not copied.
+pub(super) struct SequentialScope<'a> {
+    entries: &'a [RawEntry<'a>],
+    values: Vec<Cow<'a, str>>,
+    bindings: HashMap<Cow<'a, str>, Binding>,
+}
+
+impl<'a> SequentialScope<'a> {
+    pub(super) fn new(entries: &'a [RawEntry<'a>]) -> Self {
+        SequentialScope {
+            entries,
+            values: Vec::with_capacity(entries.len()),
+            bindings: HashMap::with_capacity(entries.len()),
+        }
+    }
+
+    /// Records the resolved value of entry `idx`; entries must be defined in order
+    pub(super) fn define(&mut self, idx: usize, value: Cow<'a, str>) {
+        debug_assert_eq!(idx, self.values.len());
+        self.values.push(value);
+        self.bindings.insert(Cow::Borrowed(self.entries[idx].key()), Binding::Entry(idx));
+    }
+
+    pub(super) fn into_values(self) -> Vec<Cow<'a, str>> {
+        self.values
+    }
+}
+
+impl Scope for SequentialScope<'_> {
+    fn lookup(&mut self, name: &str) -> Result<Option<(String, ReferenceSource)>, ParseError> {
+        Ok(match self.bindings.get(name) {
+            Some(Binding::Entry(idx)) => Some((self.values[*idx].to_string(), file_source(&self.entries[*idx]))),
+            Some(Binding::Assigned(value)) => Some((value.clone(), ReferenceSource::Default)),
+            None => system(name),
+        })
+    }
+
+    fn assign(&mut self, name: &str, value: String) {
+        self.bindings.insert(Cow::Owned(name.to_string()), Binding::Assigned(value));
+    }
+}
+
+/// Order-independent mode: references are resolved on demand against the whole file
+pub(super) struct GraphScope<'a> {
+    entries: &'a [RawEntry<'a>],
+    options: &'a ParseOptions,
+    /// Every definition of each key, in file order
+    positions: HashMap<&'a str, Vec<usize>>,
+    resolved: Vec<Option<(Cow<'a, str>, Vec<Reference>)>>,
+    /// Entries currently being resolved, used to report cycles
+    stack: Vec<usize>,
+    /// Entries whose references `resolve_entry` has already walked
+    visited: Vec<bool>,
+    assigned: HashMap<String, String>,
+    pub(super) diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> GraphScope<'a> {
+    pub(super) fn new(entries: &'a [RawEntry<'a>], options: &'a ParseOptions) -> Self {
+        let mut positions: HashMap<&str, Vec<usize>> = HashMap::with_capacity(entries.len());
+        for (idx, entry) in entries.iter().enumerate() {
+            positions.entry(entry.key()).or_default().push(idx);
+        }
+        GraphScope {
+            entries,
+            options,
+            positions,
+            resolved: (0..entries.len()).map(|_| None).collect(),
+            stack: Vec::new(),
+            visited: vec![false; entries.len()],
+            assigned: HashMap::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// The value and references of every entry, once all of them are resolved
+    pub(super) fn into_values(self) -> Vec<(Cow<'a, str>, Vec<Reference>)> {
+        self.resolved.into_iter().map(|resolved| resolved.expect("every entry is resolved")).collect()
+    }
+
+    /// Resolves entry `idx` and, before it, the entries its text refers to, deepest first.
+    /// The references are walked with an explicit stack rather than by recursing through
+    /// `lookup`, so a long chain (`A0=${A1}`, `A1=${A2}`, ...) cannot overflow the call stack.
+    /// Cycles are left to `resolve`, which reports them with the chain that was followed.
+    pub(super) fn resolve_entry(&mut self, idx: usize) -> Result<(), ParseError> {
+        let entries = self.entries;
+        // (entry, whether its references have been pushed already)
+        let mut pending = vec![(idx, false)];
+        while let Some((cur, expanded)) = pending.pop() {
+            if expanded {
+                self.resolve(cur)?;
+                continue;
+            }
+            if self.visited[cur] {
+                continue;
+            }
+            self.visited[cur] = true;
+            pending.push((cur, true));
+            if entries[cur].style == QuoteStyle::Single {
+                continue;
+            }
+            let targets: Vec<usize> =
+                referenced_names(entries[cur].value()).filter_map(|name| self.target(name, Some(cur))).collect();
+            // Pushed in reverse so they resolve in the order they appear, as `lookup` would
+            for &target in targets.iter().rev() {
+                if !self.visited[target] {
+                    pending.push((target, false));
+                } else if self.resolved[target].is_none() {
+                    // Still being walked: a cycle, unless the reference sits in a branch that is never
+                    // taken. Resolving it directly reports the cycle from `target`, like `lookup` would.
+                    self.resolve(target)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The definition a reference to `name` from entry `from` means: the last one in the file,
+    /// except that "PATH=${PATH};C:\bin" refers to the previous PATH rather than itself
+    fn target(&self, name: &str, from: Option<usize>) -> Option<usize> {
+        let positions = self.positions.get(name).map(Vec::as_slice).unwrap_or_default();
+        match from {
+            Some(cur) if self.entries[cur].key() == name => {
+                positions[..positions.partition_point(|&idx| idx < cur)].last().copied()
+            }
+            _ => positions.last().copied(),
+        }
+    }
+
+    fn resolve(&mut self, idx: usize) -> Result<(), ParseError> {
+        if self.resolved[idx].is_some() {
+            return Ok(());
+        }
+
+        let entries = self.entries;
+        if let Some(pos) = self.stack.iter().position(|&i| i == idx) {
+            let chain = self.stack[pos..]
+                .iter()
+                .chain(std::iter::once(&idx))
+                .map(|&i| entries[i].key().to_string())
+                .collect();
+            // Report the cycle on the entry that closed the loop
+            let current = &entries[*self.stack.last().unwrap_or(&idx)];
+            return Err(ParseError::Cycle {
+                path: current.source.path.clone(),
+                line: current.line,
+                chain,
+            });
+        }
+
+        self.stack.push(idx);
+        let result = resolve_value(&entries[idx], self.options, self);
+        self.stack.pop();
+
+        let Resolved { value, references, diagnostics } = result?;
+        self.diagnostics.extend(diagnostics);
+        self.resolved[idx] = Some((value, references));
+        Ok(())
+    }
+}
+
+impl Scope for GraphScope<'_> {
+    fn lookup(&mut self, name: &str) -> Result<Option<(String, ReferenceSource)>, ParseError> {
+        if let Some(idx) = self.target(name, self.stack.last().copied()) {
+            self.resolve(idx)?;
+            let value = self.resolved[idx].as_ref().map(|(value, _)| value.to_string());
+            return Ok(value.map(|value| (value, file_source(&self.entries[idx]))));
+        }
+        let assigned = self.assigned.get(name).map(|value| (value.clone(), ReferenceSource::Default));
+        Ok(assigned.or_else(|| system(name)))
+    }
+
+    fn assign(&mut self, name: &str, value: String) {
+        self.assigned.insert(name.to_string(), value);
+    }
+}
+
+/// The names `value` may refer to through `$NAME` or `${NAME...}`, defaults included.
+/// Only the text is read, so escaped dollars and branches that are never taken show up too.
+fn referenced_names(value: &str) -> impl Iterator<Item = &str> {
+    value.match_indices('$').filter_map(move |(idx, _)| {
+        let rest = &value[idx + 1..];
+        let rest = rest.strip_prefix('{').unwrap_or(rest);
+        let len = rest.find(|c: char| c != '_' && !c.is_ascii_alphanumeric()).unwrap_or(rest.len());
+        (len > 0).then(|| &rest[..len])
+    })
+}
+
+/// The outcome of `resolve_value`. The value borrows the entry's text when nothing had to change.
+pub(super) struct Resolved<'e> {
+    pub(super) value: Cow<'e, str>,
+    pub(super) references: Vec<Reference>,
+    /// Unresolved references and disabled command substitutions
+    pub(super) diagnostics: Vec<Diagnostic>,
+}
+
+/// Resolves one entry's value and reports the references that stayed unresolved.
+/// Escape sequences are decoded in the same pass as interpolation so "\${X}" stays literal.
+/// Single-quoted values are never interpolated, just like in a POSIX shell.
+/// `$(...)` only runs when `options.exec` is set; otherwise it is kept literally and reported.
+/// Which forms are recognised at all depends on `options.dialect`.
+pub(super) fn resolve_value<'e>(
+    entry: &'e RawEntry<'_>,
+    options: &ParseOptions,
+    scope: &mut dyn Scope,
+) -> Result<Resolved<'e>, ParseError> {
+    let rules = options.dialect.rules();
+    let raw = entry.value();
+    let literal = |value| Ok(Resolved { value, references: Vec::new(), diagnostics: Vec::new() });
+    if entry.style == QuoteStyle::Single {
+        if rules.single_escapes && raw.contains('\\') {
+            // python-dotenv decodes only these two inside single quotes
+            let decoded = raw.replace("\\\\", "\\").replace("\\'", "'");
+            return literal(if decoded == raw { Cow::Borrowed(raw) } else { Cow::Owned(decoded) });
+        }
+        return literal(Cow::Borrowed(raw));
+    }
+    // Without '$' or '\' there is nothing to expand or decode
+    if !raw.contains(['$', '\\']) {
+        return literal(Cow::Borrowed(raw));
+    }
+
+    let mut interpolator = Interpolator {
+        scope,
+        rules,
+        style: entry.style,
+        exec: options.exec.as_ref().filter(|_| entry.source.commands),
+        references: Vec::new(),
+        unresolved: Vec::new(),
+        skipped: Vec::new(),
+    };
+    let value = match interpolator.expand(raw, 0) {
+        // "C:\tools" or "a $ b" come out as they went in
+        Ok(value) if value == raw => Cow::Borrowed(raw),
+        Ok(value) => Cow::Owned(value),
+        Err(ExpandError::Parse(err)) => return Err(err),
+        Err(ExpandError::Required { offset, name, message }) => {
+            let (line, column) = entry.position(offset);
+            return Err(ParseError::UnresolvedReference {
+                path: entry.source.path.clone(),
+                line,
+                column,
+                snippet: entry.source.snippet(line),
+                name,
+                message,
+            });
+        }
+        Err(ExpandError::Command { offset, command, message }) => {
+            let (line, column) = entry.position(offset);
+            return Err(ParseError::Command {
+                path: entry.source.path.clone(),
+                line,
+                column,
+                snippet: entry.source.snippet(line),
+                command,
+                message,
+            });
+        }
+    };
+
+    let outcome = if rules.keep_unresolved { "was kept literally" } else { "expanded to an empty string" };
+    let unresolved = interpolator.unresolved.into_iter().map(|(offset, name)| {
+        (offset, DiagnosticKind::UnresolvedReference, format!("unresolved reference '{}' {}", name, outcome))
+    });
+    let reason = match options.exec {
+        Some(_) => format!("is in {}, which is not trusted,", entry.source.path.display()),
+        None => "is disabled".to_string(),
+    };
+    let skipped = interpolator.skipped.into_iter().map(|(offset, command)| {
+        let message = format!("command substitution '$({})' {} and was kept literally", command, reason);
+        (offset, DiagnosticKind::CommandSubstitution, message)
+    });
+    let diagnostics = unresolved
+        .chain(skipped)
+        .map(|(offset, kind, message)| {
+            let (line, column) = entry.position(offset);
+            Diagnostic {
+                kind,
+                path: entry.source.path.clone(),
+                line,
+                column,
+                snippet: entry.source.snippet(line),
+                message,
+                related_line: None,
+            }
+        })
+        .collect();
+
+    Ok(Resolved { value, references: interpolator.references, diagnostics })
+}
+
+enum ExpandError {
+    /// `${VAR:?message}` with VAR unset; `offset` points at the '$'
+    Required { offset: usize, name: String, message: String },
+    /// `$(command)` failed or timed out; `offset` points at the '$'
+    Command { offset: usize, command: String, message: String },
+    /// Raised while resolving another entry (already carries its own location)
+    Parse(ParseError),
+}
+
+impl From<ParseError> for ExpandError {
+    fn from(err: ParseError) -> Self {
+        ExpandError::Parse(err)
+    }
+}
+
+struct Interpolator<'s> {
+    scope: &'s mut dyn Scope,
+    rules: &'static Rules,
+    /// Double-quoted values also decode backslash sequences
+    style: QuoteStyle,
+    /// Run `$(...)` through this shell; `None` keeps it literally
+    exec: Option<&'s ExecOptions>,
+    /// Every variable looked up, once per name
+    references: Vec<Reference>,
+    /// References that matched nothing: (byte offset of the '$' in the value, name)
+    unresolved: Vec<(usize, String)>,
+    /// Command substitutions left alone because `exec` is off: (offset of the '$', command)
+    skipped: Vec<(usize, String)>,
+}
+
+impl Interpolator<'_> {
+    fn record(&mut self, name: &str, source: ReferenceSource) {
+        if !self.references.iter().any(|r| r.name == name) {
+            self.references.push(Reference { name: name.to_string(), source });
+        }
+    }
+
+    /// Replaces ${KEY} and $KEY with the value found through the scope (file variables or system env).
+    /// "$(command)" is replaced by the command's output when execution is enabled.
+    /// "$$" and "\$" produce a literal dollar sign.
+    /// `base` is the byte offset of `value` inside the entry's value, used for error positions.
+    fn expand(&mut self, value: &str, base: usize) -> Result<String, ExpandError> {
+        let mut result = String::with_capacity(value.len());
+        let mut chars = value.char_indices().peekable();
+
+        let interpolation = self.rules.interpolation;
+        let bare = matches!(interpolation, Interpolation::Simple | Interpolation::Posix | Interpolation::Full);
+
+        while let Some((idx, c)) = chars.next() {
+            if self.style == QuoteStyle::Double && c == '\\' {
+                push_escape(&mut chars, &mut result, self.rules.escapes);
+            } else if c == '\\' && self.rules.unquoted_escapes {
+                // Shell-style: the backslash escapes the next character, a line break is a continuation
+                match chars.next() {
+                    Some((_, '\n')) => {}
+                    Some((_, next)) => result.push(next),
+                    None => result.push('\\'),
+                }
+            } else if c == '\\' && bare && matches!(chars.peek(), Some((_, '$'))) {
+                // Unquoted values only know the "\$" escape
+                chars.next();
+                result.push('$');
+            } else if c == '$' {
+                match chars.peek().map(|&(_, next)| next) {
+                    Some('$') if matches!(interpolation, Interpolation::Posix | Interpolation::Full) => {
+                        chars.next();
+                        result.push('$');
+                    }
+                    Some('{') if interpolation != Interpolation::None => {
+                        // Found "${", verify closing "}" (defaults may nest: ${A:-${B}})
+                        chars.next(); // Consume '{'
+
+                        let inner_start = idx + 2;
+                        let mut inner_end = value.len();
+                        let mut depth = 0;
+                        let mut closed = false;
+
+                        // Read until the matching '}'
+                        for (inner_idx, inner_c) in chars.by_ref() {
+                            match inner_c {
+                                '{' => depth += 1,
+                                '}' if depth == 0 => {
+                                    inner_end = inner_idx;
+                                    closed = true;
+                                    break;
+                                }
+                                '}' => depth -= 1,
+                                _ => {}
+                            }
+                        }
+
+                        let inner = &value[inner_start..inner_end];
+                        if closed {
+                            result.push_str(&self.expand_braced(inner, base + idx, base + inner_start)?);
+                        } else {
+                            // Malformed (no closing bracket), treat as literal text
+                            result.push_str("${");
+                            result.push_str(inner);
+                        }
+                    }
+                    Some('(') if self.rules.commands => {
+                        chars.next(); // Consume '('
+
+                        let command_start = idx + 2;
+                        let mut command_end = value.len();
+                        let mut depth = 0;
+                        let mut closed = false;
+                        for (inner_idx, inner_c) in chars.by_ref() {
+                            match inner_c {
+                                '(' => depth += 1,
+                                ')' if depth == 0 => {
+                                    command_end = inner_idx;
+                                    closed = true;
+                                    break;
+                                }
+                                ')' => depth -= 1,
+                                _ => {}
+                            }
+                        }
+
+                        let command = &value[command_start..command_end];
+                        if !closed {
+                            result.push_str("$(");
+                            result.push_str(command);
+                        } else if let Some(options) = self.exec {
+                            let output = exec::run(command.trim(), options).map_err(|message| ExpandError::Command {
+                                offset: base + idx,
+                                command: command.trim().to_string(),
+                                message,
+                            })?;
+                            result.push_str(&output);
+                        } else {
+                            result.push_str("$(");
+                            result.push_str(command);
+                            result.push(')');
+                            self.skipped.push((base + idx, command.trim().to_string()));
+                        }
+                    }
+                    Some(next) if bare && (next == '_' || next.is_ascii_alphabetic()) => {
+                        // Bare "$NAME": identifier characters only
+                        let mut var_name = String::new();
+                        while let Some(&(_, inner_c)) = chars.peek() {
+                            if inner_c != '_' && !inner_c.is_ascii_alphanumeric() {
+                                break;
+                            }
+                            var_name.push(inner_c);
+                            chars.next();
+                        }
+                        match self.scope.lookup(&var_name)? {
+                            Some((val, source)) => {
+                                result.push_str(&val);
+                                self.record(&var_name, source);
+                            }
+                            None => {
+                                self.record(&var_name, ReferenceSource::Unset);
+                                if self.rules.keep_unresolved {
+                                    result.push('$');
+                                    result.push_str(&var_name);
+                                }
+                                self.unresolved.push((base + idx, var_name));
+                            }
+                        }
+                    }
+                    // Just a standalone '$', push it
+                    _ => result.push('$'),
+                }
+            } else {
+                result.push(c);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Expands the inside of "${...}", including the POSIX operators
+    /// `-`, `=`, `?` and `+` (each optionally prefixed with `:` to also treat empty as unset).
+    fn expand_braced(&mut self, inner: &str, dollar: usize, base: usize) -> Result<String, ExpandError> {
+        let name_len = inner
+            .find(|c: char| c != '_' && !c.is_ascii_alphanumeric())
+            .unwrap_or(inner.len());
+        let (name, rest) = inner.split_at(name_len);
+        let (colon, rest) = match rest.strip_prefix(':') {
+            Some(after) => (true, after),
+            None => (false, rest),
+        };
+
+        let mut op_chars = rest.chars();
+        let op = op_chars.next();
+        let word = op_chars.as_str();
+        let word_base = base + inner.len() - word.len();
+
+        // Operators the dialect does not know leave the whole expression as written
+        let supported = match self.rules.interpolation {
+            Interpolation::None => false,
+            Interpolation::Simple => op.is_none(),
+            Interpolation::Braced => op.is_none() || (colon && op == Some('-')),
+            Interpolation::Posix => op != Some('='),
+            Interpolation::Full => true,
+        };
+        if name.is_empty() || !supported {
+            return Ok(format!("${{{}}}", inner));
+        }
+
+        let (value, source) = match self.scope.lookup(name)? {
+            Some((value, source)) => (Some(value), source),
+            None => (None, ReferenceSource::Unset),
+        };
+        // With ':' an empty value counts as unset, exactly like the shell
+        let is_set = match &value {
+            Some(v) => !colon || !v.is_empty(),
+            None => false,
+        };
+        let source = match op {
+            Some('-' | '=') if !is_set => ReferenceSource::Default,
+            _ => source,
+        };
+        self.record(name, source);
+
+        match op {
+            None => match value {
+                Some(val) => Ok(val),
+                // Not found? Keep the placeholder to make the problem visible
+                None => {
+                    self.unresolved.push((dollar, name.to_string()));
+                    Ok(if self.rules.keep_unresolved { format!("${{{}}}", inner) } else { String::new() })
+                }
+            },
+            Some('-') if is_set => Ok(value.unwrap_or_default()),
+            Some('-') => self.expand(word, word_base),
+            Some('=') if is_set => Ok(value.unwrap_or_default()),
+            Some('=') => {
+                let default = self.expand(word, word_base)?;
+                self.scope.assign(name, default.clone());
+                Ok(default)
+            }
+            Some('?') if is_set => Ok(value.unwrap_or_default()),
+            Some('?') => {
+                let message = self.expand(word, word_base)?;
+                let message = if message.is_empty() {
+                    format!("{}: parameter null or not set", name)
+                } else {
+                    format!("{}: {}", name, message)
+                };
+                Err(ExpandError::Required { offset: dollar, name: name.to_string(), message })
+            }
+            Some('+') if is_set => self.expand(word, word_base),
+            Some('+') => Ok(String::new()),
+            // Unknown operator, keep the text as written
+            Some(_) => Ok(format!("${{{}}}", inner)),
+        }
+    }
+}
+
+/// Decodes one escape sequence (the leading backslash is already consumed).
+/// Unknown sequences are kept verbatim, matching python-dotenv and docker compose;
+/// Ruby instead drops the backslash.
+fn push_escape(chars: &mut Peekable<CharIndices>, result: &mut String, escapes: Escapes) {
+    let Some((_, c)) = chars.next() else {
+        result.push('\\');
+        return;
+    };
+    let decoded = match (escapes, c) {
+        (_, 'n') if escapes != Escapes::Systemd => Some('\n'),
+        (_, 'r') if escapes != Escapes::Systemd => Some('\r'),
+        (Escapes::Native | Escapes::Python, 't') => Some('\t'),
+        (Escapes::Native, '"' | '\\' | '$') => Some(c),
+        (Escapes::Python, '"' | '\\' | '\'') => Some(c),
+        (Escapes::Python, 'a') => Some('\u{7}'),
+        (Escapes::Python, 'b') => Some('\u{8}'),
+        (Escapes::Python, 'f') => Some('\u{c}'),
+        (Escapes::Python, 'v') => Some('\u{b}'),
+        (Escapes::Systemd, '"' | '\\' | '$' | '`') => Some(c),
+        (Escapes::Ruby, _) => Some(c),
+        _ => None,
+    };
+    match c {
+        _ if decoded.is_some() => result.extend(decoded),
+        'u' if escapes == Escapes::Native && matches!(chars.peek(), Some((_, '{'))) => {
+            // "\u{XXXX}": look ahead for the hex digits and the closing brace
+            let hex: String = chars.clone().skip(1).map(|(_, c)| c).take_while(|c| *c != '}').collect();
+            let len = hex.chars().count();
+            let closed = matches!(chars.clone().nth(len + 1), Some((_, '}')));
+            match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                Some(ch) if closed => {
+                    chars.nth(len + 1); // Consume "{XXXX}"
+                    result.push(ch);
+                }
+                _ => result.push_str("\\u"),
+            }
+        }
+        other => {
+            result.push('\\');
+            result.push(other);
+        }
+    }
+}
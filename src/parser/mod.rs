@@ -0,0 +1,891 @@
+mod dialect;
+mod document;
+mod encoding;
+mod error;
+mod exec;
+mod interpolate;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::trust;
+
+pub use dialect::Dialect;
+pub use document::{quote_value, Assignment, Document, Item, ItemKind};
+pub use encoding::Encoding;
+pub use error::{Diagnostic, DiagnosticKind, ParseError};
+pub use exec::ExecOptions;
+use dialect::{InlineComments, Rules};
+use interpolate::{resolve_value, GraphScope, Resolved, SequentialScope};
+
+#[derive(Debug, Clone)]
+pub struct EnvVar {
+    pub key: String,
+    pub value: String,
+    /// The file and line of the definition that won (an included file for included keys)
+    pub path: PathBuf,
+    pub line: usize,
+    /// 1-based column where the key starts on `line`
+    pub column: usize,
+    /// The value as written, with its quotes, escapes and references (a trailing comment is not included)
+    pub raw: String,
+    pub quote: QuoteStyle,
+    /// The definition was written as `export KEY=value`
+    pub export: bool,
+    /// The variables interpolation looked up for this value, in order of first use
+    pub references: Vec<Reference>,
+}
+
+/// One `${VAR}` / `$VAR` used by a value, and what it resolved to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    pub name: String,
+    pub source: ReferenceSource,
+}
+
+/// Where the value of a reference came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReferenceSource {
+    /// A definition in the parsed files
+    File { path: PathBuf, line: usize },
+    /// The process environment
+    System,
+    /// The reference was unset, so the default of `${VAR:-default}` / `${VAR:=default}` was used,
+    /// or an earlier `${VAR:=default}` assigned it
+    Default,
+    /// Nothing: the reference was kept literally or expanded to an empty string
+    Unset,
+}
+
+/// Knobs for `parse_env_file_with`. `ParseOptions::default()` matches `parse_env_file`.
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Read the whole file first and resolve references as a dependency graph,
+    /// so `URL=${HOST}/api` may appear above `HOST=...`. Cycles are reported as errors.
+    pub order_independent: bool,
+    /// Turn every diagnostic (malformed lines, invalid or duplicate keys,
+    /// unresolved references) into a `ParseError::Strict`.
+    pub strict: bool,
+    /// Which key names are accepted. Entries with other keys are skipped and reported.
+    pub key_rule: KeyRule,
+    /// What to do when a key is defined more than once
+    pub duplicates: DuplicatePolicy,
+    /// Force an encoding instead of detecting it from the BOM and byte pattern
+    pub encoding: Option<Encoding>,
+    /// Whose syntax rules to follow (docker compose, python-dotenv, ...)
+    pub dialect: Dialect,
+    /// Run `$(...)` command substitutions with these settings. `None` (the default)
+    /// keeps them literally and reports a `DiagnosticKind::CommandSubstitution`.
+    pub exec: Option<ExecOptions>,
+}
+
+/// Which definition of a repeated key is used. Every repeat is reported as a diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// The last definition wins, like a shell running the file top to bottom
+    #[default]
+    LastWins,
+    /// The first definition wins; later ones are ignored
+    FirstWins,
+    /// Any repeat is a `ParseError::DuplicateKey`
+    Error,
+}
+
+/// Naming rule for keys. Anything outside it is skipped with a `DiagnosticKind::InvalidKey`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyRule {
+    /// `[A-Za-z_][A-Za-z0-9_]*`, the only names every shell can reference directly
+    #[default]
+    Posix,
+    /// POSIX plus `-`, `.` and `:` after the first character (`my-key`, `spring.profile`)
+    Relaxed,
+    /// Any non-empty name without whitespace, quotes, `=` or control characters
+    Any,
+}
+
+impl KeyRule {
+    pub fn allows(self, key: &str) -> bool {
+        let mut chars = key.chars();
+        let Some(first) = chars.next() else {
+            return false;
+        };
+        match self {
+            KeyRule::Posix => {
+                (first == '_' || first.is_ascii_alphabetic())
+                    && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+            }
+            KeyRule::Relaxed => {
+                (first == '_' || first.is_ascii_alphabetic())
+                    && chars.all(|c| c == '_' || c == '-' || c == '.' || c == ':' || c.is_ascii_alphanumeric())
+            }
+            KeyRule::Any => key
+                .chars()
+                .all(|c| !c.is_whitespace() && !c.is_control() && !matches!(c, '"' | '\'' | '=')),
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            KeyRule::Posix => "expected letters, digits and '_', not starting with a digit",
+            KeyRule::Relaxed => "expected letters, digits, '_', '-', '.' and ':', starting with a letter or '_'",
+            KeyRule::Any => "whitespace, quotes, '=' and control characters are not allowed",
+        }
+    }
+}
+
+/// The variables of a file plus the warnings found while reading it
+pub struct ParseOutput {
+    pub vars: Vec<EnvVar>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// One `KEY=value` entry after quotes and comments are handled, before interpolation.
+/// The key and value are byte ranges into the source text rather than copies, so scanning
+/// a file allocates nothing per entry unless a value spans several lines.
+#[derive(Clone)]
+struct RawEntry<'t> {
+    /// The file the entry was read from (the parsed file or one it includes)
+    source: Rc<Source<'t>>,
+    /// Byte range of the key in `source.text`
+    key: Range<usize>,
+    /// Byte range of the value (inside its quotes) in `self.text()`
+    value: Range<usize>,
+    style: QuoteStyle,
+    /// Byte range of the value as written, quotes included, in `self.text()`
+    raw: Range<usize>,
+    /// A value continued over several lines, joined the way the scanner reads it.
+    /// `value` and `raw` point into this instead of the source when it is set.
+    joined: Option<String>,
+    /// The line started with "export "
+    export: bool,
+    line: usize,
+    /// 1-based column where `key` starts on `line`
+    key_column: usize,
+    /// 1-based column where `value` starts on `line`
+    column: usize,
+}
+
+impl RawEntry<'_> {
+    fn key(&self) -> &str {
+        &self.source.text[self.key.clone()]
+    }
+
+    fn value(&self) -> &str {
+        &self.text()[self.value.clone()]
+    }
+
+    fn raw(&self) -> &str {
+        &self.text()[self.raw.clone()]
+    }
+
+    fn text(&self) -> &str {
+        self.joined.as_deref().unwrap_or(&self.source.text)
+    }
+
+    /// Line and column of a byte offset inside the value (which may span lines)
+    fn position(&self, offset: usize) -> (usize, usize) {
+        let prefix = &self.value()[..offset];
+        match prefix.rfind('\n') {
+            None => (self.line, self.column + prefix.chars().count()),
+            Some(nl) => (
+                self.line + prefix.matches('\n').count(),
+                prefix[nl + 1..].chars().count() + 1,
+            ),
+        }
+    }
+}
+
+/// A file being parsed (the top-level one or an include), kept around to point diagnostics at the right spot
+struct Source<'t> {
+    path: PathBuf,
+    /// Content with the BOM removed and newlines normalized, borrowed from the caller when possible
+    text: Cow<'t, str>,
+    /// `$(...)` in this file may run (see `commands_allowed`)
+    commands: bool,
+}
+
+impl<'t> Source<'t> {
+    /// Takes the text as is; only CRLF line endings (or a BOM in an owned buffer) cost a copy
+    fn new(path: PathBuf, text: Cow<'t, str>, commands: bool) -> Self {
+        let text = match text {
+            Cow::Borrowed(text) => normalize_newlines(text),
+            Cow::Owned(text) => {
+                let normalized = match normalize_newlines(&text) {
+                    Cow::Borrowed(normalized) if normalized.len() == text.len() => None,
+                    other => Some(other.into_owned()),
+                };
+                Cow::Owned(normalized.unwrap_or(text))
+            }
+        };
+        Source { path, text, commands }
+    }
+
+    fn snippet(&self, line: usize) -> String {
+        self.text.lines().nth(line - 1).unwrap_or_default().to_string()
+    }
+}
+
+/// What the scanner found on a logical line
+enum Scanned<'t> {
+    Entry(RawEntry<'t>),
+    /// `# @include path` or `source path`, not yet resolved
+    Include { target: String, line: usize, column: usize },
+}
+
+/// Name used in diagnostics for input that did not come from a file
+const STRING_SOURCE: &str = "<string>";
+const READER_SOURCE: &str = "<reader>";
+const STDIN_SOURCE: &str = "<stdin>";
+
+/// Parses a .env file. Warnings are discarded; use `parse_env_file_with` to get them.
+pub fn parse_env_file(path: &str) -> Result<Vec<EnvVar>, ParseError> {
+    parse_env_path(path)
+}
+
+pub fn parse_env_file_with(path: &str, options: &ParseOptions) -> Result<ParseOutput, ParseError> {
+    parse_env_path_with(path, options)
+}
+
+/// Like `parse_env_file`, for any `Path`, `PathBuf`, `OsStr` or string
+pub fn parse_env_path<P: AsRef<Path>>(path: P) -> Result<Vec<EnvVar>, ParseError> {
+    parse_env_path_with(path, &ParseOptions::default()).map(|output| output.vars)
+}
+
+pub fn parse_env_path_with<P: AsRef<Path>>(path: P, options: &ParseOptions) -> Result<ParseOutput, ParseError> {
+    let file_path = path.as_ref();
+
+    let bytes = read_file(file_path)?;
+    let commands = commands_allowed(file_path, &bytes, options);
+    let decoded = decode_bytes(file_path, bytes, options.encoding)?;
+    parse_source(file_path, Cow::Owned(decoded.text), commands, options)
+}
+
+/// One file of a layered load. Files are applied in order, so later ones override earlier ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvFile {
+    pub path: PathBuf,
+    /// A missing optional file is skipped instead of being a `ParseError::NotFound`
+    pub optional: bool,
+}
+
+impl EnvFile {
+    pub fn required<P: Into<PathBuf>>(path: P) -> Self {
+        EnvFile { path: path.into(), optional: false }
+    }
+
+    pub fn optional<P: Into<PathBuf>>(path: P) -> Self {
+        EnvFile { path: path.into(), optional: true }
+    }
+}
+
+/// The cascade Vite and Next.js use for `mode`, next to `base` (usually `.env`):
+/// `.env`, `.env.local`, `.env.<mode>`, `.env.<mode>.local`. Every file is optional.
+pub fn mode_files<P: AsRef<Path>>(base: P, mode: &str) -> Vec<EnvFile> {
+    let base = base.as_ref().as_os_str();
+    [String::new(), ".local".to_string(), format!(".{}", mode), format!(".{}.local", mode)]
+        .into_iter()
+        .map(|suffix| {
+            let mut path = base.to_os_string();
+            path.push(suffix);
+            EnvFile::optional(path)
+        })
+        .collect()
+}
+
+/// Parses several files as one: keys from later files override earlier ones and
+/// `${VAR}` sees everything defined before it. Fails with `ParseError::NotFound`
+/// for the first file if none of them exist.
+pub fn parse_env_files_with(files: &[EnvFile], options: &ParseOptions) -> Result<ParseOutput, ParseError> {
+    let mut entries = Vec::new();
+    let mut diagnostics = Vec::new();
+    let mut first_found: Option<&Path> = None;
+
+    for file in files {
+        let bytes = match read_file(&file.path) {
+            Ok(bytes) => bytes,
+            Err(ParseError::NotFound { .. }) if file.optional => continue,
+            Err(err) => return Err(err),
+        };
+        let commands = commands_allowed(&file.path, &bytes, options);
+        let decoded = decode_bytes(&file.path, bytes, options.encoding)?;
+        entries.extend(collect_file(&file.path, Cow::Owned(decoded.text), commands, options, &mut diagnostics)?);
+        first_found.get_or_insert(&file.path);
+    }
+
+    match (first_found, files.first()) {
+        (Some(path), _) => resolve_entries(path, entries, diagnostics, options),
+        (None, Some(file)) => Err(ParseError::NotFound { path: file.path.clone() }),
+        (None, None) => Ok(ParseOutput { vars: Vec::new(), diagnostics }),
+    }
+}
+
+/// Parses .env content held in memory. Diagnostics refer to it as `<string>`.
+pub fn parse_env_str(content: &str) -> Result<Vec<EnvVar>, ParseError> {
+    parse_env_str_with(content, &ParseOptions::default()).map(|output| output.vars)
+}
+
+pub fn parse_env_str_with(content: &str, options: &ParseOptions) -> Result<ParseOutput, ParseError> {
+    let path = Path::new(STRING_SOURCE);
+    parse_source(path, Cow::Borrowed(content), commands_allowed(path, content.as_bytes(), options), options)
+}
+
+/// Reads everything from `reader` and parses it. Diagnostics refer to it as `<reader>`.
+pub fn parse_env_reader<R: Read>(reader: R) -> Result<Vec<EnvVar>, ParseError> {
+    parse_env_reader_with(reader, &ParseOptions::default()).map(|output| output.vars)
+}
+
+pub fn parse_env_reader_with<R: Read>(reader: R, options: &ParseOptions) -> Result<ParseOutput, ParseError> {
+    read_and_parse(reader, Path::new(READER_SOURCE), options)
+}
+
+/// Parses standard input (e.g. output piped from a secret manager). Diagnostics say `<stdin>`.
+pub fn parse_env_stdin_with(options: &ParseOptions) -> Result<ParseOutput, ParseError> {
+    read_and_parse(io::stdin().lock(), Path::new(STDIN_SOURCE), options)
+}
+
+fn read_and_parse<R: Read>(mut reader: R, name: &Path, options: &ParseOptions) -> Result<ParseOutput, ParseError> {
+    let mut bytes = Vec::new();
+    reader
+        .read_to_end(&mut bytes)
+        .map_err(|source| ParseError::Io { path: name.to_path_buf(), source })?;
+    let commands = commands_allowed(name, &bytes, options);
+    let decoded = decode_bytes(name, bytes, options.encoding)?;
+    parse_source(name, Cow::Owned(decoded.text), commands, options)
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, ParseError> {
+    fs::read(path).map_err(|source| match source.kind() {
+        // Return clear error if file missing
+        io::ErrorKind::NotFound => ParseError::NotFound { path: path.to_path_buf() },
+        _ => ParseError::Io { path: path.to_path_buf(), source },
+    })
+}
+
+fn decode_bytes(path: &Path, bytes: Vec<u8>, forced: Option<Encoding>) -> Result<encoding::Decoded, ParseError> {
+    encoding::decode(bytes, forced).map_err(|message| ParseError::Encoding { path: path.to_path_buf(), message })
+}
+
+/// Drops a leading BOM character and turns CRLF into LF so values never end in '\r'
+fn normalize_newlines(content: &str) -> Cow<'_, str> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    if content.contains('\r') {
+        Cow::Owned(content.replace("\r\n", "\n"))
+    } else {
+        Cow::Borrowed(content)
+    }
+}
+
+/// Shared by every entry point: `path` labels diagnostics and anchors relative includes
+fn parse_source(
+    path: &Path,
+    content: Cow<'_, str>,
+    commands: bool,
+    options: &ParseOptions,
+) -> Result<ParseOutput, ParseError> {
+    let mut diagnostics = Vec::new();
+    let entries = collect_file(path, content, commands, options, &mut diagnostics)?;
+    resolve_entries(path, entries, diagnostics, options)
+}
+
+/// The entries of one top-level file, including the files it pulls in
+fn collect_file<'t>(
+    path: &Path,
+    content: Cow<'t, str>,
+    commands: bool,
+    options: &ParseOptions,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<Vec<RawEntry<'t>>, ParseError> {
+    let source = Rc::new(Source::new(path.to_path_buf(), content, commands));
+    let mut stack: Vec<(PathBuf, PathBuf)> = fs::canonicalize(path)
+        .map(|canonical| vec![(canonical, path.to_path_buf())])
+        .unwrap_or_default();
+    collect_entries(source, &mut stack, options, diagnostics)
+}
+
+/// Whether `$(...)` in the file at `path` may run: always once `exec` is set, unless
+/// `exec.trusted_only` asks for every file (an include as much as the file including it) to be trusted.
+/// `content` is the bytes read for parsing, so trust covers exactly what runs.
+fn commands_allowed(path: &Path, content: &[u8], options: &ParseOptions) -> bool {
+    options.exec.as_ref().is_some_and(|exec| !exec.trusted_only || trust::is_trusted_content(path, content))
+}
+
+/// Interpolates, checks and deduplicates the entries gathered from one or more files.
+/// `path` names the whole input in a `ParseError::Strict`.
+fn resolve_entries(
+    path: &Path,
+    mut entries: Vec<RawEntry<'_>>,
+    mut diagnostics: Vec<Diagnostic>,
+    options: &ParseOptions,
+) -> Result<ParseOutput, ParseError> {
+    check_keys(&mut entries, options.key_rule, &mut diagnostics);
+    check_duplicates(&mut entries, options.duplicates, &mut diagnostics)?;
+
+    // Interpolate (Resolve ${VAR} placeholders)
+    // Values borrow from the entries' text until they are turned into `EnvVar`s
+    let values: Vec<(Cow<'_, str>, Vec<Reference>)> = if options.order_independent {
+        let mut scope = GraphScope::new(&entries, options);
+        for idx in 0..entries.len() {
+            scope.resolve_entry(idx)?;
+        }
+        diagnostics.append(&mut scope.diagnostics);
+        scope.into_values()
+    } else {
+        // The scope sees the variables defined in previous lines
+        let mut scope = SequentialScope::new(&entries);
+        let mut references = Vec::with_capacity(entries.len());
+        for (idx, entry) in entries.iter().enumerate() {
+            let Resolved { value, references: used, diagnostics: warnings } = resolve_value(entry, options, &mut scope)?;
+            diagnostics.extend(warnings);
+            scope.define(idx, value);
+            references.push(used);
+        }
+        scope.into_values().into_iter().zip(references).collect()
+    };
+    // Files in the order their entries apply, so warnings read top to bottom
+    let mut files: Vec<&Path> = Vec::new();
+    let mut previous: Option<&Rc<Source<'_>>> = None;
+    for entry in &entries {
+        // Neighbouring entries usually share a file, so the paths are only compared when the source changes
+        if previous.is_some_and(|source| Rc::ptr_eq(source, &entry.source)) {
+            continue;
+        }
+        previous = Some(&entry.source);
+        if !files.contains(&entry.source.path.as_path()) {
+            files.push(&entry.source.path);
+        }
+    }
+    let rank = |d: &Diagnostic| files.iter().position(|f| *f == d.path).unwrap_or(files.len());
+    diagnostics.sort_by_key(|d| (rank(d), d.line, d.column));
+
+    if options.strict && !diagnostics.is_empty() {
+        return Err(ParseError::Strict { path: path.to_path_buf(), diagnostics });
+    }
+
+    // Only the winning definition of each key is returned (first-wins already dropped the rest)
+    let mut last_index: HashMap<&str, usize> = HashMap::with_capacity(entries.len());
+    for (idx, entry) in entries.iter().enumerate() {
+        last_index.insert(entry.key(), idx);
+    }
+
+    let vars = entries
+        .iter()
+        .zip(values)
+        .enumerate()
+        .filter(|(idx, (entry, _))| last_index[entry.key()] == *idx)
+        .map(|(_, (entry, (value, references)))| EnvVar {
+            key: entry.key().to_string(),
+            value: value.into_owned(),
+            path: entry.source.path.clone(),
+            line: entry.line,
+            column: entry.key_column,
+            raw: entry.raw().to_string(),
+            quote: entry.style,
+            export: entry.export,
+            references,
+        })
+        .collect();
+    Ok(ParseOutput { vars, diagnostics })
+}
+
+/// Scans `source` and splices in the entries of every file it includes, at the point of the directive.
+/// `stack` holds the files being included (canonical path, path as written) to detect cycles.
+fn collect_entries<'t>(
+    source: Rc<Source<'t>>,
+    stack: &mut Vec<(PathBuf, PathBuf)>,
+    options: &ParseOptions,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<Vec<RawEntry<'t>>, ParseError> {
+    let mut entries = Vec::new();
+    for item in scan_entries(&source, options.dialect.rules(), diagnostics)? {
+        let (target, line, column) = match item {
+            Scanned::Entry(entry) => {
+                entries.push(entry);
+                continue;
+            }
+            Scanned::Include { target, line, column } => (target, line, column),
+        };
+        let include_error = |message: String| ParseError::Include {
+            path: source.path.clone(),
+            line,
+            column,
+            snippet: source.snippet(line),
+            message,
+        };
+
+        // Relative paths resolve against the including file, not the working directory
+        let path = source.path.parent().unwrap_or(Path::new("")).join(&target);
+        let canonical = fs::canonicalize(&path)
+            .map_err(|_| include_error(format!("included file '{}' was not found", path.display())))?;
+        if let Some(pos) = stack.iter().position(|(seen, _)| *seen == canonical) {
+            let chain: Vec<String> = stack[pos..]
+                .iter()
+                .map(|(_, shown)| shown.display().to_string())
+                .chain(std::iter::once(path.display().to_string()))
+                .collect();
+            return Err(include_error(format!("include cycle detected: {}", chain.join(" -> "))));
+        }
+
+        let bytes = read_file(&path)?;
+        let commands = commands_allowed(&path, &bytes, options);
+        let decoded = decode_bytes(&path, bytes, options.encoding)?;
+        let included = Rc::new(Source::new(path.clone(), Cow::Owned(decoded.text), commands));
+        stack.push((canonical, path));
+        entries.extend(collect_entries(included, stack, options, diagnostics)?);
+        stack.pop();
+    }
+    Ok(entries)
+}
+
+/// Splits the file into logical entries: skips comments, joins multiline quotes, strips quotes
+fn scan_entries<'t>(
+    source: &Rc<Source<'t>>,
+    rules: &Rules,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<Vec<Scanned<'t>>, ParseError> {
+    let lines: Vec<&str> = source.text.lines().collect();
+    let mut entries: Vec<Scanned> = Vec::new();
+
+    let mut line_idx = 0;
+    while line_idx < lines.len() {
+        let line_num = line_idx + 1;
+        let line = lines[line_idx];
+        let mut trimmed = line.trim();
+        line_idx += 1;
+
+        if let Some(target) = include_target(trimmed).filter(|_| rules.includes) {
+            entries.push(Scanned::Include { target: target.to_string(), line: line_num, column: column_of(line, target) });
+            continue;
+        }
+
+        // Skip comments and empty lines
+        if trimmed.is_empty() || trimmed.starts_with(rules.comment_chars) {
+            continue;
+        }
+
+        // Many .env files use "export VAR=VAL". We must strip "export " 
+        // so the key becomes just "VAR".
+        let export = match trimmed.strip_prefix("export ").filter(|_| rules.export) {
+            Some(rest) => {
+                trimmed = rest.trim();
+                true
+            }
+            None => false,
+        };
+
+        // Split by first '='
+        if let Some((key_part, value_part)) = trimmed.split_once('=') {
+            let key_part = key_part.trim();
+            let key_column = column_of(line, key_part);
+            let value_part = value_part.trim();
+            let value_column = column_of(line, value_part);
+            // Only built when the value continues on the next line; otherwise `value_part` is used as is
+            let mut joined: Option<String> = None;
+
+            // A quoted value may span several physical lines (PEM keys, JSON blobs).
+            // Keep consuming lines until the matching closing quote shows up.
+            if let Some(quote) = open_quote(value_part, rules) {
+                // From the opening quote to the end of the line, so spaces inside the quotes survive
+                let raw_value = joined.insert(line[span_of(line, value_part).start..].to_string());
+                loop {
+                    if line_idx >= lines.len() {
+                        return Err(ParseError::Syntax {
+                            path: source.path.clone(),
+                            line: line_num,
+                            column: value_column,
+                            snippet: line.to_string(),
+                            message: format!(
+                                "unterminated {} quote in value of '{}'",
+                                match quote {
+                                    '"' => "double",
+                                    '\'' => "single",
+                                    _ => "backtick",
+                                },
+                                key_part
+                            ),
+                        });
+                    }
+                    raw_value.push('\n');
+                    raw_value.push_str(lines[line_idx]);
+                    line_idx += 1;
+                    if find_closing_quote(&raw_value[1..], quote, rules).is_some() {
+                        raw_value.truncate(raw_value.trim_end().len());
+                        break;
+                    }
+                }
+            } else if rules.unquoted_escapes && ends_with_escape(value_part) && line_idx < lines.len() {
+                // systemd: a trailing backslash continues the value on the next line
+                let raw_value = joined.insert(value_part.to_string());
+                while ends_with_escape(raw_value) && line_idx < lines.len() {
+                    raw_value.push('\n');
+                    raw_value.push_str(lines[line_idx].trim_end());
+                    line_idx += 1;
+                }
+            }
+            let raw_value = joined.as_deref().unwrap_or(value_part);
+
+            // Only a comment may follow a closing quote, which sits on the last line read
+            if let Some(junk) = text_after_quote(raw_value, rules) {
+                let closing = lines[line_idx - 1];
+                return Err(ParseError::Syntax {
+                    path: source.path.clone(),
+                    line: line_idx,
+                    column: column_of(closing, &closing[closing.trim_end().len() - junk.len()..]),
+                    snippet: closing.to_string(),
+                    message: format!("unexpected text after the closing quote of '{}'", key_part),
+                });
+            }
+
+            // Remove quotes if present (e.g., "value" -> value)
+            let (style, mut clean_value) = strip_quotes(raw_value, rules);
+
+            // Unquoted values may carry a trailing comment: "PORT=8080 # dev only"
+            if style == QuoteStyle::None {
+                clean_value = strip_inline_comment(clean_value, rules.inline_comments);
+            }
+            // Quoted: the opening quote, the body and the closing quote (all quote characters are one byte)
+            let raw = match style {
+                QuoteStyle::None => clean_value,
+                _ => &raw_value[..clean_value.len() + 2],
+            };
+            let base = joined.as_deref().unwrap_or(&source.text);
+            let (value, raw) = (span_of(base, clean_value), span_of(base, raw));
+
+            entries.push(Scanned::Entry(RawEntry {
+                source: Rc::clone(source),
+                key: span_of(&source.text, key_part),
+                value,
+                style,
+                raw,
+                joined,
+                export,
+                line: line_num,
+                key_column,
+                column: if style == QuoteStyle::None { value_column } else { value_column + 1 },
+            }));
+        } else {
+            diagnostics.push(Diagnostic {
+                kind: DiagnosticKind::Malformed,
+                path: source.path.clone(),
+                line: line_num,
+                column: column_of(line, trimmed),
+                snippet: line.to_string(),
+                message: "line is malformed (missing '='), skipping".to_string(),
+                related_line: None,
+            });
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Drops keys the rule rejects and reports them
+fn check_keys(entries: &mut Vec<RawEntry<'_>>, rule: KeyRule, diagnostics: &mut Vec<Diagnostic>) {
+    entries.retain(|entry| {
+        let allowed = rule.allows(entry.key());
+        if !allowed {
+            diagnostics.push(Diagnostic {
+                kind: DiagnosticKind::InvalidKey,
+                path: entry.source.path.clone(),
+                line: entry.line,
+                column: entry.key_column,
+                snippet: entry.source.snippet(entry.line),
+                message: format!("'{}' is not a valid variable name ({}), skipping", entry.key(), rule.describe()),
+                related_line: None,
+            });
+        }
+        allowed
+    });
+}
+
+/// Reports keys defined more than once and applies the policy:
+/// first-wins drops the later entries here, last-wins keeps them all until after interpolation
+/// (earlier lines may still reference the earlier value).
+/// A key from an included file that is redefined in another file is an override, not a duplicate.
+/// Under first-wins, a file that redefines a key after an include overrode it still gets its
+/// first definition: the later entry is replaced by a copy of the first instead of dropped.
+fn check_duplicates(
+    entries: &mut Vec<RawEntry<'_>>,
+    policy: DuplicatePolicy,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<(), ParseError> {
+    // Index of the first definition of each key per file
+    let mut first_seen: HashMap<(*const Source<'_>, &str), usize> = HashMap::new();
+    // File of the definition of each key currently in effect
+    let mut latest: HashMap<&str, *const Source<'_>> = HashMap::new();
+    let mut keep = vec![true; entries.len()];
+    let mut copies: Vec<(usize, RawEntry)> = Vec::new();
+
+    for (idx, entry) in entries.iter().enumerate() {
+        let source = Rc::as_ptr(&entry.source);
+        let first = match first_seen.get(&(source, entry.key())) {
+            Some(&first) => first,
+            None => {
+                first_seen.insert((source, entry.key()), idx);
+                latest.insert(entry.key(), source);
+                continue;
+            }
+        };
+        let first_line = entries[first].line;
+
+        let winner = match policy {
+            DuplicatePolicy::LastWins => entry.line,
+            DuplicatePolicy::FirstWins => first_line,
+            DuplicatePolicy::Error => {
+                return Err(ParseError::DuplicateKey {
+                    path: entry.source.path.clone(),
+                    line: entry.line,
+                    column: entry.key_column,
+                    snippet: entry.source.snippet(entry.line),
+                    key: entry.key().to_string(),
+                    first_line,
+                });
+            }
+        };
+        match policy {
+            DuplicatePolicy::FirstWins if latest.get(entry.key()) == Some(&source) => keep[idx] = false,
+            DuplicatePolicy::FirstWins => copies.push((idx, entries[first].clone())),
+            _ => {}
+        }
+        latest.insert(entry.key(), source);
+        diagnostics.push(Diagnostic {
+            kind: DiagnosticKind::DuplicateKey,
+            path: entry.source.path.clone(),
+            line: entry.line,
+            column: entry.key_column,
+            snippet: entry.source.snippet(entry.line),
+            message: format!(
+                "duplicate key '{}' (first defined on line {}, redefined on line {}); line {} wins",
+                entry.key(), first_line, entry.line, winner
+            ),
+            related_line: Some(first_line),
+        });
+    }
+
+    for (idx, copy) in copies {
+        entries[idx] = copy;
+    }
+    let mut keep = keep.into_iter();
+    entries.retain(|_| keep.next().unwrap_or(true));
+    Ok(())
+}
+
+/// The path of an include directive: `# @include path` or `source path` (optionally quoted)
+fn include_target(line: &str) -> Option<&str> {
+    let rest = match line.strip_prefix('#') {
+        Some(comment) => comment.trim_start().strip_prefix("@include")?,
+        None => line.strip_prefix("source")?,
+    };
+    // "# @included by ..." is a comment and "source=x" an assignment
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let target = match strip_quotes(rest.trim(), Dialect::Native.rules()) {
+        (QuoteStyle::None, target) => strip_inline_comment(target, InlineComments::AfterWhitespace),
+        (_, target) => target,
+    };
+    (!target.is_empty() && !target.starts_with('=')).then_some(target)
+}
+
+/// Byte range of `part`, a slice of `text`, inside `text`
+fn span_of(text: &str, part: &str) -> Range<usize> {
+    let start = part.as_ptr() as usize - text.as_ptr() as usize;
+    start..start + part.len()
+}
+
+/// 1-based column of `part`, which must be a slice of `line`
+fn column_of(line: &str, part: &str) -> usize {
+    let offset = part.as_ptr() as usize - line.as_ptr() as usize;
+    line[..offset].chars().count() + 1
+}
+
+/// Text other than a comment after the closing quote of a quoted value, e.g. `junk` in `"a" junk`
+fn text_after_quote<'s>(s: &'s str, rules: &Rules) -> Option<&'s str> {
+    let quote = s.chars().next().filter(|c| *c == '"' || *c == '\'' || (*c == '`' && rules.backticks))?;
+    let end = find_closing_quote(&s[1..], quote, rules)?;
+    let after = &s[end + 2..];
+    (!is_blank_or_comment(after)).then(|| after.trim_start())
+}
+
+/// Returns the quote character if the value opens a quote that is not closed on the same line
+fn open_quote(s: &str, rules: &Rules) -> Option<char> {
+    let quote = s.chars().next().filter(|c| *c == '"' || *c == '\'' || (*c == '`' && rules.backticks))?;
+    match find_closing_quote(&s[1..], quote, rules) {
+        Some(_) => None,
+        None => Some(quote),
+    }
+}
+
+/// Finds the byte offset of the closing quote. Backslash escapes are honoured in double quotes
+/// (and in single quotes for dialects that decode them there).
+fn find_closing_quote(s: &str, quote: char, rules: &Rules) -> Option<usize> {
+    let escapes = quote == '"' || (quote == '\'' && rules.single_escapes);
+    let mut escaped = false;
+    for (idx, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' && escapes {
+            escaped = true;
+        } else if c == quote {
+            return Some(idx);
+        }
+    }
+    None
+}
+
+/// How a value was quoted in the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteStyle {
+    None,
+    Single,
+    Double,
+}
+
+/// Removes surrounding "" or '' from a string and reports which style was used.
+/// Backtick quotes (where the dialect has them) are literal, so they report `Single`.
+fn strip_quotes<'s>(s: &'s str, rules: &Rules) -> (QuoteStyle, &'s str) {
+    let (style, quote) = match s.chars().next() {
+        Some('"') => (QuoteStyle::Double, '"'),
+        Some('\'') => (QuoteStyle::Single, '\''),
+        Some('`') if rules.backticks => (QuoteStyle::Single, '`'),
+        _ => return (QuoteStyle::None, s),
+    };
+    let body = &s[1..];
+    match find_closing_quote(body, quote, rules) {
+        Some(end) if is_blank_or_comment(&body[end + 1..]) => (style, &body[..end]),
+        _ => (QuoteStyle::None, s),
+    }
+}
+
+/// True for text that is empty or only holds a comment (what may follow a closing quote)
+fn is_blank_or_comment(s: &str) -> bool {
+    let rest = s.trim();
+    rest.is_empty() || rest.starts_with('#')
+}
+
+/// Cuts an unquoted value at its trailing comment. Natively that is the first '#' preceded
+/// by whitespace, so a '#' in the middle of a token (e.g. "http://x/#anchor") is kept.
+fn strip_inline_comment(s: &str, comments: InlineComments) -> &str {
+    let mut prev_is_space = false;
+    for (idx, c) in s.char_indices() {
+        let starts_comment = match comments {
+            InlineComments::AfterWhitespace => prev_is_space,
+            InlineComments::Anywhere => true,
+            InlineComments::Never => false,
+        };
+        if c == '#' && starts_comment {
+            return s[..idx].trim_end();
+        }
+        prev_is_space = c.is_whitespace();
+    }
+    s
+}
+
+/// True if the text ends in an unescaped backslash
+fn ends_with_escape(s: &str) -> bool {
+    s.chars().rev().take_while(|c| *c == '\\').count() % 2 == 1
+}
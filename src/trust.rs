@@ -0,0 +1,103 @@
+//! Per-file trust for `$(...)` command substitution.
+//!
+//! Trusting a file records its canonical path together with a SHA-256 of its
+//! content. Any later edit changes the hash, so the file has to be trusted again
+//! before its commands run (the same model direnv uses for `.envrc`).
+//!
+//! The store is a plain text file with one `<sha256>  <path>` line per file:
+//! `%APPDATA%\ruster-env\trusted` on Windows, `$XDG_CONFIG_HOME/ruster-env/trusted`
+//! (or `~/.config/ruster-env/trusted`) elsewhere. `RUSTER_ENV_CONFIG_DIR` overrides the directory.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+const STORE_NAME: &str = "trusted";
+
+/// Where the trust store lives, or `None` if no config directory can be found
+pub fn store_path() -> Option<PathBuf> {
+    let dir = match std::env::var_os("RUSTER_ENV_CONFIG_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None if cfg!(windows) => PathBuf::from(std::env::var_os("APPDATA")?).join("ruster-env"),
+        None => match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) => PathBuf::from(dir).join("ruster-env"),
+            None => PathBuf::from(std::env::var_os("HOME")?).join(".config").join("ruster-env"),
+        },
+    };
+    Some(dir.join(STORE_NAME))
+}
+
+/// True if `path` was trusted and has not changed since.
+/// Any problem reading the file or the store counts as "not trusted".
+pub fn is_trusted<P: AsRef<Path>>(path: P) -> bool {
+    fs::read(path.as_ref()).is_ok_and(|content| is_trusted_content(path, &content))
+}
+
+/// Like `is_trusted`, for `content` already read from `path`. The parser checks the bytes
+/// it is about to use, so the file cannot change between the check and the parse.
+pub fn is_trusted_content<P: AsRef<Path>>(path: P, content: &[u8]) -> bool {
+    let Ok(canonical) = fs::canonicalize(path.as_ref()) else {
+        return false;
+    };
+    let hash = sha256(content);
+    read_store().unwrap_or_default().iter().any(|(h, p)| *p == canonical && *h == hash)
+}
+
+/// Trusts the current content of `path`, replacing any earlier entry for it
+pub fn trust<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    let canonical = fs::canonicalize(path.as_ref())?;
+    let hash = sha256(&fs::read(&canonical)?);
+    let mut entries = read_store()?;
+    entries.retain(|(_, p)| *p != canonical);
+    entries.push((hash, canonical));
+    write_store(&entries)
+}
+
+/// Forgets `path`. Returns false if it was not trusted.
+pub fn revoke<P: AsRef<Path>>(path: P) -> io::Result<bool> {
+    let canonical = fs::canonicalize(path.as_ref())?;
+    let mut entries = read_store()?;
+    let before = entries.len();
+    entries.retain(|(_, p)| *p != canonical);
+    if entries.len() == before {
+        return Ok(false);
+    }
+    write_store(&entries)?;
+    Ok(true)
+}
+
+fn sha256(content: &[u8]) -> String {
+    Sha256::digest(content).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn missing_store() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "no config directory for the trust store (set RUSTER_ENV_CONFIG_DIR)")
+}
+
+fn read_store() -> io::Result<Vec<(String, PathBuf)>> {
+    let store = store_path().ok_or_else(missing_store)?;
+    let content = match fs::read_to_string(&store) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(content
+        .lines()
+        .filter_map(|line| line.split_once("  "))
+        .map(|(hash, path)| (hash.to_string(), PathBuf::from(path)))
+        .collect())
+}
+
+fn write_store(entries: &[(String, PathBuf)]) -> io::Result<()> {
+    let store = store_path().ok_or_else(missing_store)?;
+    if let Some(dir) = store.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let content: String = entries
+        .iter()
+        .map(|(hash, path)| format!("{}  {}\n", hash, path.display()))
+        .collect();
+    fs::write(store, content)
+}
@@ -147,4 +147,361 @@ fn test_show_single_var() {
            // Ensure it prints ONLY the value (no headers) so it's clean for scripts
            predicate::str::contains("System Environment Variables").not()
        ));
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_load_order_independent() {
+    let file = create_temp_env("URL=${HOST}/api\nHOST=localhost");
+    let path = file.path().to_str().unwrap();
+
+    let mut cmd = cmd();
+    cmd.arg("load")
+       .arg(path)
+       .arg("--order-independent")
+       .arg("--shell").arg("cmd")
+       .assert()
+       .success()
+       .stdout(predicate::str::contains("SET \"URL=localhost/api\""));
+}
+
+#[test]
+fn test_load_renders_syntax_error() {
+    let file = create_temp_env("OK=1\nBROKEN=\"never closed");
+    let path = file.path().to_str().unwrap();
+
+    let mut cmd = cmd();
+    cmd.arg("load")
+       .arg(path)
+       .arg("--shell").arg("cmd")
+       .assert()
+       .failure()
+       .stderr(predicate::str::contains("error: unterminated double quote"))
+       .stderr(predicate::str::contains("2 | BROKEN=\"never closed"))
+       .stderr(predicate::str::contains("  |        ^"));
+}
+
+#[test]
+fn test_load_warns_on_malformed_line() {
+    let file = create_temp_env("SKIP_ME\nKEEP=1");
+    let path = file.path().to_str().unwrap();
+
+    let mut cmd = cmd();
+    cmd.arg("load")
+       .arg(path)
+       .arg("--shell").arg("cmd")
+       .assert()
+       .success()
+       .stdout(predicate::str::contains("SET \"KEEP=1\""))
+       .stderr(predicate::str::contains("warning: line is malformed"));
+}
+
+#[test]
+fn test_load_strict_fails_on_unresolved_reference() {
+    let file = create_temp_env("DATABASE_URL=${RUSTER_UNDEFINED_DB_HOST}");
+    let path = file.path().to_str().unwrap();
+
+    let mut cmd = cmd();
+    cmd.arg("load")
+       .arg(path)
+       .arg("--strict")
+       .arg("--shell").arg("cmd")
+       .assert()
+       .failure()
+       .stdout(predicate::str::contains("DATABASE_URL").not())
+       .stderr(predicate::str::contains("unresolved reference"));
+}
+
+#[test]
+fn test_load_powershell_unusual_key_uses_literal_path() {
+    let file = create_temp_env("my-key=it's");
+    let path = file.path().to_str().unwrap();
+
+    let mut cmd = cmd();
+    cmd.arg("load")
+       .arg(path)
+       .arg("--key-rule").arg("relaxed")
+       .arg("--shell").arg("powershell")
+       .assert()
+       .success()
+       .stdout(predicate::str::contains("Set-Item -LiteralPath 'env:my-key' -Value 'it''s';"));
+}
+
+#[test]
+fn test_load_powershell_doubles_smart_quotes() {
+    let file = create_temp_env("k\u{2019}ey=x\u{2019}; Remove-Item C:\\important; \u{2018}");
+    let path = file.path().to_str().unwrap();
+
+    let mut cmd = cmd();
+    cmd.arg("load")
+       .arg(path)
+       .arg("--key-rule").arg("any")
+       .arg("--shell").arg("powershell")
+       .assert()
+       .success()
+       .stdout(predicate::str::contains(
+           "Set-Item -LiteralPath 'env:k\u{2019}\u{2019}ey' -Value 'x\u{2019}\u{2019}; Remove-Item C:\\important; \u{2018}\u{2018}';",
+       ));
+}
+
+#[test]
+fn test_load_skips_injected_key() {
+    let file = create_temp_env("x;Remove-Item C:\\=1\nSAFE=1");
+    let path = file.path().to_str().unwrap();
+
+    let mut cmd = cmd();
+    cmd.arg("load")
+       .arg(path)
+       .arg("--shell").arg("powershell")
+       .assert()
+       .success()
+       .stdout(predicate::str::contains("Remove-Item").not())
+       .stdout(predicate::str::contains("$env:SAFE = '1';"))
+       .stderr(predicate::str::contains("is not a valid variable name"));
+}
+
+#[test]
+fn test_load_cmd_escapes_values() {
+    let file = create_temp_env("A=\"a\\\"&calc&\\\"\"\nB=100%");
+    let path = file.path().to_str().unwrap();
+
+    let mut cmd = cmd();
+    cmd.arg("load")
+       .arg(path)
+       .arg("--shell").arg("cmd")
+       .assert()
+       .success()
+       .stdout(predicate::str::contains("SET A=a^\"^&calc^&^\""))
+       .stdout(predicate::str::contains("SET \"B=100%%\""));
+}
+
+#[test]
+fn test_load_from_stdin() {
+    let mut cmd = cmd();
+    cmd.arg("load")
+       .arg("-")
+       .arg("--shell").arg("cmd")
+       .write_stdin("PIPED=from_secret_manager\n")
+       .assert()
+       .success()
+       .stdout(predicate::str::contains("SET \"PIPED=from_secret_manager\""));
+}
+
+#[test]
+fn test_set_and_unset_edit_file() {
+    let file = create_temp_env("# Database\nDB_HOST=localhost # local only\n");
+    let path = file.path().to_str().unwrap();
+
+    cmd().arg("set").arg("DB_HOST=db.internal").arg("--file").arg(path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[Ruster] Set DB_HOST"));
+    cmd().arg("set").arg("GREETING=hello world").arg("--file").arg(path)
+        .assert()
+        .success();
+    assert_eq!(
+        std::fs::read_to_string(path).unwrap(),
+        "# Database\nDB_HOST=db.internal # local only\nGREETING='hello world'\n"
+    );
+
+    cmd().arg("unset").arg("DB_HOST").arg("--file").arg(path)
+        .assert()
+        .success();
+    assert_eq!(std::fs::read_to_string(path).unwrap(), "# Database\nGREETING='hello world'\n");
+}
+
+#[test]
+fn test_set_apply_emits_session_script() {
+    let file = create_temp_env("");
+    let path = file.path().to_str().unwrap();
+
+    cmd().arg("set").arg("TOKEN=abc").arg("--file").arg(path)
+        .arg("--apply")
+        .arg("--shell").arg("powershell")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("$env:TOKEN = 'abc';"));
+}
+
+#[test]
+fn test_set_rejects_invalid_key() {
+    let file = create_temp_env("");
+    let path = file.path().to_str().unwrap();
+
+    cmd().arg("set").arg("bad key=1").arg("--file").arg(path)
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_load_deduplicates_keys() {
+    let file = create_temp_env("A=1\nA=2\nB=3");
+    let path = file.path().to_str().unwrap();
+
+    cmd().arg("load")
+       .arg(path)
+       .arg("--shell").arg("cmd")
+       .assert()
+       .success()
+       .stdout(predicate::str::contains("SET \"A=1\"").not())
+       .stdout(predicate::str::contains("SET \"A=2\""))
+       .stdout(predicate::str::contains("Loaded 2 variables"))
+       .stderr(predicate::str::contains("duplicate key 'A'"));
+}
+
+#[test]
+fn test_exec_requires_allow_exec_or_trust() {
+    let file = create_temp_env("VERSION=$(echo 1.2.3)");
+    let path = file.path().to_str().unwrap();
+    let config = tempfile::tempdir().unwrap();
+
+    let load = || {
+        let mut cmd = cmd();
+        cmd.env("RUSTER_ENV_CONFIG_DIR", config.path()).arg("load").arg(path).arg("--shell").arg("powershell");
+        cmd
+    };
+
+    load().assert()
+        .success()
+        .stdout(predicate::str::contains("$env:VERSION = '$(echo 1.2.3)';"))
+        .stderr(predicate::str::contains(format!("command substitution '$(echo 1.2.3)' is in {}, which is not trusted", path)));
+
+    load().arg("--allow-exec")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("$env:VERSION = '1.2.3';"));
+
+    cmd().env("RUSTER_ENV_CONFIG_DIR", config.path()).arg("trust").arg(path).assert().success();
+    load().assert()
+        .success()
+        .stdout(predicate::str::contains("$env:VERSION = '1.2.3';"));
+
+    // Editing the file revokes the trust
+    std::fs::write(path, "VERSION=$(echo 2.0.0)").unwrap();
+    load().assert()
+        .success()
+        .stdout(predicate::str::contains("$env:VERSION = '$(echo 2.0.0)';"));
+}
+
+#[test]
+fn test_trust_does_not_cover_includes() {
+    let dir = tempfile::tempdir().unwrap();
+    let top = dir.path().join("top.env");
+    let include = dir.path().join("inc.env");
+    std::fs::write(&top, "TOP=$(echo top)\nsource ./inc.env\n").unwrap();
+    std::fs::write(&include, "PWN=$(echo pwned)\n").unwrap();
+    let config = tempfile::tempdir().unwrap();
+
+    let trust = |path: &std::path::Path| {
+        cmd().env("RUSTER_ENV_CONFIG_DIR", config.path()).arg("trust").arg(path).assert().success();
+    };
+    let load = || {
+        let mut cmd = cmd();
+        cmd.env("RUSTER_ENV_CONFIG_DIR", config.path()).arg("load").arg(&top).arg("--shell").arg("powershell");
+        cmd
+    };
+
+    trust(&top);
+    load().assert()
+        .success()
+        .stdout(predicate::str::contains("$env:TOP = 'top';"))
+        .stdout(predicate::str::contains("$env:PWN = '$(echo pwned)';"))
+        .stderr(predicate::str::contains("inc.env, which is not trusted"));
+
+    trust(&include);
+    load().assert()
+        .success()
+        .stdout(predicate::str::contains("$env:PWN = 'pwned';"));
+}
+
+#[test]
+fn test_trust_is_checked_per_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path().join(".env");
+    let local = dir.path().join(".env.local");
+    std::fs::write(&base, "BASE=$(echo base)\n").unwrap();
+    std::fs::write(&local, "LOCAL=$(echo local)\n").unwrap();
+    let config = tempfile::tempdir().unwrap();
+    cmd().env("RUSTER_ENV_CONFIG_DIR", config.path()).arg("trust").arg(&base).assert().success();
+
+    // An untrusted file next to a trusted one does not switch off the trusted file's commands
+    cmd().env("RUSTER_ENV_CONFIG_DIR", config.path())
+        .arg("load")
+        .arg("-f").arg(&base)
+        .arg("-f").arg(&local)
+        .arg("--shell").arg("powershell")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("$env:BASE = 'base';"))
+        .stdout(predicate::str::contains("$env:LOCAL = '$(echo local)';"))
+        .stderr(predicate::str::contains(format!("is in {}, which is not trusted", local.display())));
+}
+
+#[test]
+fn test_load_mode_cascade() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path().join(".env");
+    std::fs::write(&base, "API=http://localhost\nDEBUG=1\n").unwrap();
+    std::fs::write(dir.path().join(".env.staging"), "API=https://staging\n").unwrap();
+    std::fs::write(dir.path().join(".env.staging.local"), "DEBUG=0\n").unwrap();
+
+    let expect_staging = |cmd: &mut Command| {
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("SET \"API=https://staging\""))
+            .stdout(predicate::str::contains("SET \"DEBUG=0\""))
+            .stdout(predicate::str::contains("SET \"API=http://localhost\"").not())
+            .stdout(predicate::str::contains("Loaded 2 variables"));
+    };
+
+    expect_staging(cmd().arg("load").arg(&base).arg("--mode").arg("staging").arg("--shell").arg("cmd"));
+    expect_staging(cmd().env("RUSTER_ENV", "staging").arg("load").arg(&base).arg("--shell").arg("cmd"));
+
+    // Extra files layer on top of the cascade instead of replacing it
+    std::fs::write(dir.path().join("extra.env"), "EXTRA=1\n").unwrap();
+    cmd().current_dir(dir.path())
+        .env("RUSTER_ENV", "staging")
+        .arg("load")
+        .arg("-f").arg("extra.env")
+        .arg("--shell").arg("cmd")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("SET \"API=https://staging\""))
+        .stdout(predicate::str::contains("SET \"DEBUG=0\""))
+        .stdout(predicate::str::contains("SET \"EXTRA=1\""));
+}
+
+#[test]
+fn test_load_multiple_files() {
+    let base = create_temp_env("HOST=localhost\nPORT=5432\n");
+    let local = create_temp_env("PORT=6543\nURL=${HOST}:${PORT}\n");
+    let missing = base.path().with_extension("missing");
+
+    cmd().arg("load")
+       .arg("-f").arg(base.path())
+       .arg("--optional").arg(&missing)
+       .arg("--file").arg(local.path())
+       .arg("--shell").arg("cmd")
+       .assert()
+       .success()
+       .stdout(predicate::str::contains("SET \"PORT=5432\"").not())
+       .stdout(predicate::str::contains("SET \"PORT=6543\""))
+       .stdout(predicate::str::contains("SET \"URL=localhost:6543\""))
+       .stdout(predicate::str::contains("Loaded 3 variables"));
+
+    // Left to right: the base file now wins
+    cmd().arg("load")
+       .arg("-f").arg(local.path())
+       .arg("-f").arg(base.path())
+       .arg("--shell").arg("cmd")
+       .assert()
+       .success()
+       .stdout(predicate::str::contains("SET \"PORT=5432\""));
+
+    cmd().arg("load")
+       .arg("-f").arg(base.path())
+       .arg("-f").arg(&missing)
+       .arg("--shell").arg("cmd")
+       .assert()
+       .failure()
+       .stderr(predicate::str::contains("File not found"));
+}
@@ -0,0 +1,150 @@
+#![cfg(feature = "serde")]
+
+use ruster_env::de::Error;
+use ruster_env::{from_path, from_str, from_str_with, FromEnvOptions};
+use serde::Deserialize;
+use std::io::Write;
+use tempfile::NamedTempFile;
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Database {
+    host: String,
+    port: u16,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Config {
+    name: String,
+    port: u16,
+    ratio: f64,
+    debug: bool,
+    timeout: Option<u32>,
+    proxy: Option<String>,
+    hosts: Vec<String>,
+    db: Database,
+}
+
+#[test]
+fn test_deserialize_struct() {
+    let content = r#"
+NAME="my app"
+PORT=8080
+RATIO=0.5
+DEBUG=yes
+PROXY=
+HOSTS=a.example, b.example,c.example
+DB__HOST=localhost
+DB__PORT=${PORT}
+"#;
+    let config: Config = from_str(content).unwrap();
+
+    assert_eq!(
+        config,
+        Config {
+            name: "my app".to_string(),
+            port: 8080,
+            ratio: 0.5,
+            debug: true,
+            timeout: None,
+            proxy: None,
+            hosts: vec!["a.example".to_string(), "b.example".to_string(), "c.example".to_string()],
+            db: Database { host: "localhost".to_string(), port: 8080 },
+        }
+    );
+}
+
+#[test]
+fn test_deserialize_prefix() {
+    #[derive(Debug, Deserialize)]
+    struct App {
+        port: u16,
+        workers: Vec<u8>,
+    }
+
+    let options = FromEnvOptions { prefix: Some("APP_".to_string()), ..FromEnvOptions::default() };
+    let app: App = from_str_with("PORT=1\nAPP_PORT=3000\nAPP_WORKERS=1,2\n", &options).unwrap();
+
+    assert_eq!(app.port, 3000);
+    assert_eq!(app.workers, vec![1, 2]);
+}
+
+#[test]
+fn test_deserialize_invalid_value_names_key_and_line() {
+    let mut file = NamedTempFile::new().unwrap();
+    write!(file, "NAME=app\n\nPORT=eighty\n").unwrap();
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Small {
+        name: String,
+        port: u16,
+    }
+
+    let err = from_path::<Small, _>(file.path()).unwrap_err();
+    match &err {
+        Error::Value { key, line, .. } => {
+            assert_eq!(key, "PORT");
+            assert_eq!(*line, 3);
+        }
+        other => panic!("expected a value error, got {:?}", other),
+    }
+    let message = err.to_string();
+    assert!(message.contains("PORT") && message.contains(":3:"), "{}", message);
+    assert!(message.contains("eighty"), "{}", message);
+}
+
+#[test]
+fn test_deserialize_missing_nested_key() {
+    let err = from_str::<Config>("NAME=x\nPORT=1\nRATIO=1\nDEBUG=0\nHOSTS=\nDB__HOST=h\n").unwrap_err();
+
+    assert!(matches!(&err, Error::Missing { key } if key == "DB__PORT"), "{:?}", err);
+    assert_eq!(err.to_string(), "missing variable DB__PORT");
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Outer {
+        app: Config,
+    }
+    let options = FromEnvOptions { prefix: Some("X_".to_string()), ..FromEnvOptions::default() };
+    let err = from_str_with::<Outer>("X_APP__DB__HOST=h\nX_APP__DB__PORT=1\n", &options).unwrap_err();
+    assert!(matches!(&err, Error::Missing { key } if key == "X_APP__NAME"), "{:?}", err);
+    let err = from_str_with::<Outer>("X_APP__DB__HOST=h\n", &options).unwrap_err();
+    assert!(matches!(&err, Error::Missing { key } if key == "X_APP__DB__PORT"), "{:?}", err);
+}
+
+#[test]
+fn test_deserialize_parse_error() {
+    let options = FromEnvOptions {
+        parse: ruster_env::parser::ParseOptions { strict: true, ..Default::default() },
+        ..FromEnvOptions::default()
+    };
+    let err = from_str_with::<Database>("HOST=${NOPE:?must be set}\n", &options).unwrap_err();
+
+    assert!(matches!(err, Error::Parse(_)), "{:?}", err);
+}
+
+#[test]
+fn test_deserialize_skips_unknown_nested_keys() {
+    #[derive(Debug, Deserialize)]
+    struct Small {
+        port: u16,
+    }
+
+    let small: Small = from_str("PORT=1\nOTHER__X=2\n").unwrap();
+    assert_eq!(small.port, 1);
+}
+
+#[test]
+fn test_deserialize_renamed_fields() {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+    struct Service {
+        database_url: String,
+        #[serde(rename = "maxConnections")]
+        max_connections: u8,
+    }
+
+    let service: Service = from_str("DATABASE_URL=postgres://db\nMAXCONNECTIONS=4\n").unwrap();
+    assert_eq!(service.database_url, "postgres://db");
+    assert_eq!(service.max_connections, 4);
+}
@@ -0,0 +1,190 @@
+use ruster_env::parser::{parse_env_str, quote_value, Document, ItemKind, QuoteStyle};
+
+const SAMPLE: &str = "# Service config\r\n\
+\r\n\
+export  API_KEY = \"abc\"   # keep me\r\n\
+PORT=8080 # dev only\n\
+   INDENTED='x'\n\
+CERT=\"-----BEGIN-----\r\nline\r\n-----END-----\"\n\
+MALFORMED LINE\n\
+\t\n\
+EMPTY=\n\
+LAST=no-newline";
+
+#[test]
+fn test_round_trip_is_byte_for_byte() {
+    let doc = Document::parse(SAMPLE).unwrap();
+
+    assert_eq!(doc.to_string(), SAMPLE);
+}
+
+#[test]
+fn test_round_trip_keeps_bom() {
+    let content = format!("\u{feff}{}", SAMPLE);
+    let doc = Document::parse(&content).unwrap();
+
+    assert!(doc.bom);
+    assert_eq!(doc.items.len(), 10);
+    assert_eq!(doc.to_string(), content);
+}
+
+#[test]
+fn test_items_keep_structure() {
+    let doc = Document::parse(SAMPLE).unwrap();
+
+    assert_eq!(doc.items.len(), 10);
+    assert!(matches!(doc.items[0].kind, ItemKind::Comment(_)));
+    assert!(matches!(doc.items[1].kind, ItemKind::Blank(_)));
+    assert!(matches!(doc.items[6].kind, ItemKind::Invalid(_)));
+    assert_eq!(doc.items[0].newline, "\r\n");
+    assert_eq!(doc.items[9].newline, "");
+
+    let keys: Vec<&str> = doc.assignments().map(|a| a.key.as_str()).collect();
+    assert_eq!(keys, vec!["API_KEY", "PORT", "INDENTED", "CERT", "EMPTY", "LAST"]);
+}
+
+#[test]
+fn test_assignment_pieces() {
+    let doc = Document::parse(SAMPLE).unwrap();
+
+    let api = doc.get("API_KEY").unwrap();
+    assert_eq!(api.export, "export  ");
+    assert_eq!(api.before_eq, " ");
+    assert_eq!(api.after_eq, " ");
+    assert_eq!(api.raw_value, "\"abc\"");
+    assert_eq!(api.quote, QuoteStyle::Double);
+    assert_eq!(api.trailing, "   # keep me");
+
+    let port = doc.get("PORT").unwrap();
+    assert_eq!(port.raw_value, "8080");
+    assert_eq!(port.trailing, " # dev only");
+
+    let cert = doc.get("CERT").unwrap();
+    assert_eq!(cert.line, 6);
+    assert!(cert.raw_value.contains("\r\nline\r\n"));
+}
+
+#[test]
+fn test_edit_keeps_comments() {
+    let mut doc = Document::parse("# header\nPORT=8080 # dev only\n").unwrap();
+    doc.get_mut("PORT").unwrap().raw_value = "9090".to_string();
+
+    assert_eq!(doc.to_string(), "# header\nPORT=9090 # dev only\n");
+}
+
+#[test]
+fn test_unterminated_quote_is_an_error() {
+    assert!(Document::parse("A=\"open\nB=1").is_err());
+}
+
+#[test]
+fn test_text_after_closing_quote_is_an_error() {
+    for content in ["A=\"x\" junk\n", "A=\"x\ny\" junk\n"] {
+        let err = Document::parse(content).unwrap_err();
+        assert_eq!(err.to_string(), parse_env_str(content).unwrap_err().to_string());
+    }
+    assert!(Document::parse("A=\"x\ny\" # note\n").is_ok());
+}
+
+#[test]
+fn test_set_updates_in_place_and_appends() {
+    let mut doc = Document::parse("# db\r\nHOST=old # primary\r\nPORT=1").unwrap();
+    doc.set("HOST", "new host");
+    doc.set("USER", "admin");
+
+    assert_eq!(doc.to_string(), "# db\r\nHOST='new host' # primary\r\nPORT=1\r\nUSER=admin\r\n");
+}
+
+#[test]
+fn test_remove_keeps_comments() {
+    let mut doc = Document::parse("# keep\nA=1\nB=2\nA=3\n").unwrap();
+
+    assert!(doc.remove("A"));
+    assert!(!doc.remove("MISSING"));
+    assert_eq!(doc.to_string(), "# keep\nB=2\n");
+}
+
+#[test]
+fn test_quote_value_round_trips_through_parser() {
+    let samples = ["plain", "", "with space", "it's", "$HOME and ${X}", "a\"b\\c", "line1\nline2", "#hash"];
+    for sample in samples {
+        let (raw, _) = quote_value(sample);
+        let vars = parse_env_str(&format!("K={}", raw)).unwrap();
+        assert_eq!(vars[0].value, sample, "raw form was {}", raw);
+    }
+
+    assert_eq!(quote_value("plain").1, QuoteStyle::None);
+    assert_eq!(quote_value("it's").1, QuoteStyle::Double);
+}
+
+#[test]
+fn test_save_writes_atomically() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(".env");
+    std::fs::write(&path, "# header\nA=1\n").unwrap();
+
+    let mut doc = Document::from_path(&path).unwrap();
+    doc.set("B", "2");
+    doc.save(&path).unwrap();
+
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "# header\nA=1\nB=2\n");
+    // Only the target remains, no leftover temp file
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn test_save_keeps_utf16_encoding() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(".env");
+    let original: Vec<u8> = [0xFFu8, 0xFE]
+        .into_iter()
+        .chain("A=1\r\n".encode_utf16().flat_map(|u| u.to_le_bytes()))
+        .collect();
+    std::fs::write(&path, &original).unwrap();
+
+    let mut doc = Document::from_path(&path).unwrap();
+    doc.save(&path).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), original);
+
+    doc.set("B", "2");
+    doc.save(&path).unwrap();
+    let expected: Vec<u8> = [0xFFu8, 0xFE]
+        .into_iter()
+        .chain("A=1\r\nB=2\r\n".encode_utf16().flat_map(|u| u.to_le_bytes()))
+        .collect();
+    assert_eq!(std::fs::read(&path).unwrap(), expected);
+}
+
+#[test]
+fn test_save_keeps_utf8_bom() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(".env");
+    std::fs::write(&path, b"\xEF\xBB\xBFA=1\n").unwrap();
+
+    let mut doc = Document::from_path(&path).unwrap();
+    doc.set("B", "2");
+    doc.save(&path).unwrap();
+
+    assert_eq!(std::fs::read(&path).unwrap(), b"\xEF\xBB\xBFA=1\nB=2\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_save_keeps_permissions_and_symlinks() {
+    use std::os::unix::fs::{symlink, PermissionsExt};
+
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("real.env");
+    std::fs::write(&target, "A=1\n").unwrap();
+    std::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o600)).unwrap();
+    let link = dir.path().join(".env");
+    symlink(&target, &link).unwrap();
+
+    let mut doc = Document::from_path(&link).unwrap();
+    doc.set("B", "2");
+    doc.save(&link).unwrap();
+
+    assert!(std::fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "A=1\nB=2\n");
+    assert_eq!(std::fs::metadata(&target).unwrap().permissions().mode() & 0o777, 0o600);
+}
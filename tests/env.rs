@@ -0,0 +1,89 @@
+use ruster_env::parser::parse_env_str;
+use ruster_env::{Change, Env, MergePolicy};
+use std::collections::HashMap;
+use std::process::Command;
+
+fn env(pairs: &[(&str, &str)]) -> Env {
+    pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+#[test]
+fn test_env_keeps_order_and_replaces_in_place() {
+    let mut vars = Env::from(parse_env_str("B=1\nA=2\nC=3").unwrap());
+    assert_eq!(vars.keys().collect::<Vec<_>>(), vec!["B", "A", "C"]);
+
+    assert_eq!(vars.insert("A", "20"), Some("2".to_string()));
+    assert_eq!(vars.insert("D", "4"), None);
+    assert_eq!(vars.remove("B"), Some("1".to_string()));
+
+    assert_eq!(vars.iter().collect::<Vec<_>>(), vec![("A", "20"), ("C", "3"), ("D", "4")]);
+    assert_eq!(vars.get("C"), Some("3"));
+    assert_eq!(vars.get("B"), None);
+}
+
+#[test]
+fn test_env_get_as() {
+    let vars = env(&[("PORT", "8080"), ("NAME", "app")]);
+
+    assert_eq!(vars.get_as::<u16>("PORT"), Ok(Some(8080)));
+    assert_eq!(vars.get_as::<u16>("MISSING"), Ok(None));
+    assert!(vars.get_as::<u16>("NAME").is_err());
+}
+
+#[test]
+fn test_env_merge_policies() {
+    let base = env(&[("A", "1"), ("B", "2")]);
+    let other = env(&[("B", "20"), ("C", "30")]);
+
+    let mut merged = base.clone();
+    merged.merge(other.clone(), MergePolicy::Override);
+    assert_eq!(merged, env(&[("A", "1"), ("B", "20"), ("C", "30")]));
+
+    let mut kept = base;
+    kept.merge(other, MergePolicy::KeepExisting);
+    assert_eq!(kept, env(&[("A", "1"), ("B", "2"), ("C", "30")]));
+}
+
+#[test]
+fn test_env_diff() {
+    let before = env(&[("A", "1"), ("B", "2"), ("C", "3")]);
+    let after = env(&[("A", "1"), ("B", "20"), ("D", "4")]);
+
+    assert_eq!(
+        before.diff(&after),
+        vec![
+            Change::Changed { key: "B".to_string(), old: "2".to_string(), new: "20".to_string() },
+            Change::Removed { key: "C".to_string(), value: "3".to_string() },
+            Change::Added { key: "D".to_string(), value: "4".to_string() },
+        ]
+    );
+    assert!(before.diff(&before).is_empty());
+}
+
+#[test]
+fn test_env_retain_and_prefix() {
+    let mut vars = env(&[("APP_PORT", "80"), ("APP_", "x"), ("HOME", "/root"), ("APP_NAME", "web")]);
+
+    assert_eq!(vars.with_prefix("APP_"), env(&[("PORT", "80"), ("NAME", "web")]));
+
+    vars.retain(|key, _| key != "HOME");
+    assert_eq!(vars.keys().collect::<Vec<_>>(), vec!["APP_PORT", "APP_", "APP_NAME"]);
+    assert_eq!(vars.get("APP_NAME"), Some("web"));
+}
+
+#[test]
+fn test_env_conversions() {
+    let map: HashMap<String, String> = [("B", "2"), ("A", "1")].iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+    let vars = Env::from(map.clone());
+    assert_eq!(vars.keys().collect::<Vec<_>>(), vec!["A", "B"]);
+    assert_eq!(HashMap::from(vars.clone()), map);
+
+    std::env::set_var("RUSTER_ENV_TYPE_TEST", "yes");
+    assert_eq!(Env::from_system().get("RUSTER_ENV_TYPE_TEST"), Some("yes"));
+    assert_eq!(std::env::vars().collect::<Env>().get("RUSTER_ENV_TYPE_TEST"), Some("yes"));
+
+    let mut cmd = Command::new("program");
+    cmd.envs(&vars);
+    let set: Vec<_> = cmd.get_envs().map(|(k, v)| (k.to_str().unwrap(), v.unwrap().to_str().unwrap())).collect();
+    assert_eq!(set, vec![("A", "1"), ("B", "2")]);
+}
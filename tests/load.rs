@@ -0,0 +1,72 @@
+use ruster_env::load::{find_from, load_with, LoadOptions};
+use ruster_env::parser::ParseError;
+use ruster_env::{load_from, load_override};
+use std::env;
+use std::fs;
+
+// Tests share one process environment, so every test uses its own key names
+
+#[test]
+fn test_load_keeps_existing_variables() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(".env");
+    fs::write(&path, "LOAD_KEEP_NEW=fresh\nLOAD_KEEP_OLD=from-file\n").unwrap();
+    env::set_var("LOAD_KEEP_OLD", "from-process");
+
+    let report = load_from(&path).unwrap();
+
+    assert_eq!(report.path, path);
+    assert_eq!(report.set, vec!["LOAD_KEEP_NEW"]);
+    assert_eq!(report.skipped, vec!["LOAD_KEEP_OLD"]);
+    assert_eq!(env::var("LOAD_KEEP_NEW").unwrap(), "fresh");
+    assert_eq!(env::var("LOAD_KEEP_OLD").unwrap(), "from-process");
+}
+
+#[test]
+fn test_load_override_replaces_existing_variables() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(".env");
+    fs::write(&path, "LOAD_OVERRIDE_KEY=from-file\n").unwrap();
+    env::set_var("LOAD_OVERRIDE_KEY", "from-process");
+
+    let report = load_override(&path).unwrap();
+
+    assert_eq!(report.set, vec!["LOAD_OVERRIDE_KEY"]);
+    assert!(report.skipped.is_empty());
+    assert_eq!(env::var("LOAD_OVERRIDE_KEY").unwrap(), "from-file");
+}
+
+#[test]
+fn test_load_skips_nul_values() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(".env");
+    fs::write(&path, "LOAD_NUL_KEY=\"a\\u{0}b\"\n").unwrap();
+
+    let report = load_with(&path, &LoadOptions { overwrite: true, ..LoadOptions::default() }).unwrap();
+
+    assert_eq!(report.skipped, vec!["LOAD_NUL_KEY"]);
+    assert!(env::var_os("LOAD_NUL_KEY").is_none());
+}
+
+#[test]
+fn test_find_walks_up_to_parent_directories() {
+    let dir = tempfile::tempdir().unwrap();
+    let nested = dir.path().join("crates").join("app");
+    fs::create_dir_all(&nested).unwrap();
+    fs::write(dir.path().join(".env"), "A=1\n").unwrap();
+
+    assert_eq!(find_from(&nested, ".env"), Some(dir.path().join(".env")));
+
+    // The nearest file wins
+    fs::write(nested.join(".env"), "A=2\n").unwrap();
+    assert_eq!(find_from(&nested, ".env"), Some(nested.join(".env")));
+    assert_eq!(find_from(&nested, ".env.missing"), None);
+}
+
+#[test]
+fn test_load_missing_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let err = load_from(dir.path().join("nope.env")).unwrap_err();
+
+    assert!(matches!(err, ParseError::NotFound { .. }));
+}
@@ -1,4 +1,9 @@
-use ruster_env::parser::parse_env_file; // Import from your lib
+use ruster_env::parser::{
+    mode_files, parse_env_file, parse_env_file_with, parse_env_files_with, parse_env_path,
+    parse_env_reader, parse_env_str, parse_env_str_with, DiagnosticKind, Dialect, DuplicatePolicy,
+    Encoding, EnvFile, ExecOptions, KeyRule, ParseError, ParseOptions, QuoteStyle, Reference,
+    ReferenceSource,
+}; // Import from your lib
 use std::io::Write;
 use tempfile::NamedTempFile;
 
@@ -68,4 +73,637 @@ fn test_interpolation() {
     let vars = parse_env_file(file.path().to_str().unwrap()).unwrap();
 
     assert_eq!(vars[1].value, "http://localhost/api");
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_multiline_quoted_value() {
+    let content = "CERT=\"-----BEGIN CERT-----\nabc123\n-----END CERT-----\"\nJSON='{\n  \"a\": 1\n}'\nAFTER=ok";
+    let file = create_temp_env(content);
+    let vars = parse_env_file(file.path().to_str().unwrap()).unwrap();
+
+    assert_eq!(vars.len(), 3);
+    assert_eq!(vars[0].value, "-----BEGIN CERT-----\nabc123\n-----END CERT-----");
+    assert_eq!(vars[1].value, "{\n  \"a\": 1\n}");
+    assert_eq!(vars[2].key, "AFTER");
+
+    // Only a comment may follow the closing quote
+    let vars = parse_env_str("A=\"x\ny\" # note").unwrap();
+    assert_eq!(vars[0].value, "x\ny");
+    let err = parse_env_str("A=\"x\ny\" junk\nB=1").err().unwrap();
+    assert!(matches!(err, ParseError::Syntax { line: 2, column: 4, .. }), "{:?}", err);
+    let err = parse_env_str("A=\"x\" junk  \nB=1").err().unwrap();
+    assert!(matches!(err, ParseError::Syntax { line: 1, column: 7, .. }), "{:?}", err);
+
+    // Spaces inside the quotes at the end of the first line are part of the value
+    let vars = parse_env_str("K=\"abc   \nline2\"").unwrap();
+    assert_eq!(vars[0].value, "abc   \nline2");
+}
+
+#[test]
+fn test_unterminated_quote_reports_opening_line() {
+    let file = create_temp_env("OK=1\nBROKEN=\"never closed\nNEXT=2");
+    let err = parse_env_file(file.path().to_str().unwrap()).err().unwrap();
+
+    match err {
+        ParseError::Syntax { line, column, snippet, .. } => {
+            assert_eq!(line, 2);
+            assert_eq!(column, 8);
+            assert_eq!(snippet, "BROKEN=\"never closed");
+        }
+        other => panic!("expected a syntax error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_double_quoted_escapes() {
+    let content = r#"
+        MSG="line1\nline2\ttab\r"
+        QUOTED="say \"hi\""
+        SLASH="a\\b"
+        DOLLAR="cost \${PRICE}"
+        SNOWMAN="\u{2603}"
+    "#;
+    let file = create_temp_env(content);
+    let vars = parse_env_file(file.path().to_str().unwrap()).unwrap();
+
+    assert_eq!(vars[0].value, "line1\nline2\ttab\r");
+    assert_eq!(vars[1].value, "say \"hi\"");
+    assert_eq!(vars[2].value, "a\\b");
+    assert_eq!(vars[3].value, "cost ${PRICE}");
+    assert_eq!(vars[4].value, "\u{2603}");
+}
+
+#[test]
+fn test_single_quoted_is_literal() {
+    let file = create_temp_env(r#"RAW='C:\new\table \"x\"'"#);
+    let vars = parse_env_file(file.path().to_str().unwrap()).unwrap();
+
+    assert_eq!(vars[0].value, r#"C:\new\table \"x\""#);
+}
+
+#[test]
+fn test_single_quoted_not_interpolated() {
+    let content = r#"
+        ss=oops
+        PASSWORD='pa${ss}word'
+        DOUBLE="pa${ss}word"
+    "#;
+    let file = create_temp_env(content);
+    let vars = parse_env_file(file.path().to_str().unwrap()).unwrap();
+
+    assert_eq!(vars[1].value, "pa${ss}word");
+    assert_eq!(vars[2].value, "paoopsword");
+}
+
+#[test]
+fn test_inline_comments() {
+    let content = r#"
+        PORT=8080 # dev only
+        URL=http://x/#anchor
+        QUOTED="keep # this" # but not this
+        SINGLE='also # kept'
+    "#;
+    let file = create_temp_env(content);
+    let vars = parse_env_file(file.path().to_str().unwrap()).unwrap();
+
+    assert_eq!(vars[0].value, "8080");
+    assert_eq!(vars[1].value, "http://x/#anchor");
+    assert_eq!(vars[2].value, "keep # this");
+    assert_eq!(vars[3].value, "also # kept");
+}
+
+#[test]
+fn test_bare_interpolation_and_dollar_escapes() {
+    let content = r#"
+        BASE=/opt/app
+        DIR=$BASE/bin
+        PRICE=5$$
+        ESCAPED=\$BASE
+        LITERAL="\${BASE} and $${BASE}"
+        UNKNOWN=$RUSTER_SURELY_UNDEFINED_VAR/x
+        LONE=a $ b
+    "#;
+    let file = create_temp_env(content);
+    let vars = parse_env_file(file.path().to_str().unwrap()).unwrap();
+
+    assert_eq!(vars[1].value, "/opt/app/bin");
+    assert_eq!(vars[2].value, "5$");
+    assert_eq!(vars[3].value, "$BASE");
+    assert_eq!(vars[4].value, "${BASE} and ${BASE}");
+    assert_eq!(vars[5].value, "$RUSTER_SURELY_UNDEFINED_VAR/x");
+    assert_eq!(vars[6].value, "a $ b");
+}
+
+#[test]
+fn test_parameter_expansion_operators() {
+    let content = r#"
+        EMPTY=
+        SET=value
+        A=${RUSTER_UNDEFINED_A:-fallback}
+        B=${EMPTY:-fallback}
+        C=${EMPTY-fallback}
+        D=${RUSTER_UNDEFINED_D:=assigned}
+        E=${RUSTER_UNDEFINED_D}
+        F=${SET:+alt}
+        G=${EMPTY:+alt}
+        H=${RUSTER_UNDEFINED_H:-${SET}/nested}
+    "#;
+    let file = create_temp_env(content);
+    let vars = parse_env_file(file.path().to_str().unwrap()).unwrap();
+
+    assert_eq!(vars[2].value, "fallback");
+    assert_eq!(vars[3].value, "fallback");
+    assert_eq!(vars[4].value, "");
+    assert_eq!(vars[5].value, "assigned");
+    assert_eq!(vars[6].value, "assigned");
+    assert_eq!(vars[7].value, "alt");
+    assert_eq!(vars[8].value, "");
+    assert_eq!(vars[9].value, "value/nested");
+}
+
+#[test]
+fn test_required_variable_error() {
+    let file = create_temp_env("OK=1\nDB=${RUSTER_UNDEFINED_DB:?database host required}");
+    let path = file.path().to_str().unwrap();
+    let err = parse_env_file(path).err().unwrap();
+
+    assert!(err.to_string().contains(&format!("{}:2:4", path)));
+    match err {
+        ParseError::UnresolvedReference { name, message, .. } => {
+            assert_eq!(name, "RUSTER_UNDEFINED_DB");
+            assert!(message.contains("database host required"));
+        }
+        other => panic!("expected an unresolved reference, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_order_independent_interpolation() {
+    let content = r#"
+        URL=${HOST}/api
+        HOST=http://${DOMAIN}
+        DOMAIN=example.com
+        PATH=${PATH};C:\tools
+    "#;
+    let file = create_temp_env(content);
+    let options = ParseOptions { order_independent: true, ..Default::default() };
+    let vars = parse_env_file_with(file.path().to_str().unwrap(), &options).unwrap().vars;
+
+    assert_eq!(vars[0].value, "http://example.com/api");
+    assert_eq!(vars[1].value, "http://example.com");
+    assert_eq!(vars[3].value, format!("{};C:\\tools", std::env::var("PATH").unwrap()));
+}
+
+#[test]
+fn test_order_independent_cycle_detection() {
+    let file = create_temp_env("A=${B}\nB=${C}\nC=${A}");
+    let options = ParseOptions { order_independent: true, ..Default::default() };
+    let err = parse_env_file_with(file.path().to_str().unwrap(), &options).err().unwrap();
+
+    assert!(err.to_string().contains("A -> B -> C -> A"));
+    assert!(matches!(err, ParseError::Cycle { ref chain, .. } if chain.len() == 4));
+}
+
+#[test]
+fn test_order_independent_long_chain() {
+    // Each link used to cost a few stack frames; a chain this long overflowed the stack
+    let links = 20_000;
+    let mut content: String = (0..links).map(|i| format!("A{}=${{A{}}}\n", i, i + 1)).collect();
+    content.push_str(&format!("A{}=end\n", links));
+    let options = ParseOptions { order_independent: true, ..Default::default() };
+    let vars = parse_env_str_with(&content, &options).unwrap().vars;
+
+    assert_eq!(vars.len(), links + 1);
+    assert!(vars.iter().all(|var| var.value == "end"));
+}
+
+#[test]
+fn test_missing_file_is_not_found() {
+    let err = parse_env_file("definitely/not/here/.env").err().unwrap();
+
+    assert!(matches!(err, ParseError::NotFound { .. }));
+}
+
+#[test]
+fn test_diagnostics_are_returned() {
+    let file = create_temp_env("VALID=1\n  SKIP_ME\nURL=http://${RUSTER_UNDEFINED_HOST}/x");
+    let output = parse_env_file_with(file.path().to_str().unwrap(), &ParseOptions::default()).unwrap();
+
+    assert_eq!(output.vars.len(), 2);
+    assert_eq!(output.diagnostics.len(), 2);
+
+    let malformed = &output.diagnostics[0];
+    assert_eq!(malformed.kind, DiagnosticKind::Malformed);
+    assert_eq!((malformed.line, malformed.column), (2, 3));
+
+    let unresolved = &output.diagnostics[1];
+    assert_eq!(unresolved.kind, DiagnosticKind::UnresolvedReference);
+    assert_eq!((unresolved.line, unresolved.column), (3, 12));
+    assert_eq!(unresolved.snippet, "URL=http://${RUSTER_UNDEFINED_HOST}/x");
+}
+
+#[test]
+fn test_strict_mode_rejects_warnings() {
+    let content = "GOOD=1\nSKIP_ME\nbad-key=2\nGOOD=3\nURL=${RUSTER_UNDEFINED_STRICT}";
+    let file = create_temp_env(content);
+    let path = file.path().to_str().unwrap();
+
+    // Lenient mode keeps going
+    assert_eq!(parse_env_file(path).unwrap().len(), 2);
+
+    let options = ParseOptions { strict: true, ..Default::default() };
+    let err = parse_env_file_with(path, &options).err().unwrap();
+    let ParseError::Strict { diagnostics, .. } = err else {
+        panic!("expected a strict-mode error, got {:?}", err);
+    };
+    let kinds: Vec<DiagnosticKind> = diagnostics.iter().map(|d| d.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            DiagnosticKind::Malformed,
+            DiagnosticKind::InvalidKey,
+            DiagnosticKind::DuplicateKey,
+            DiagnosticKind::UnresolvedReference,
+        ]
+    );
+    assert!(diagnostics[2].message.contains("line 1"));
+    assert!(diagnostics[2].message.contains("line 4"));
+}
+
+#[test]
+fn test_strict_mode_accepts_clean_file() {
+    let file = create_temp_env("A=1\nB=${A}");
+    let options = ParseOptions { strict: true, ..Default::default() };
+
+    assert_eq!(parse_env_file_with(file.path().to_str().unwrap(), &options).unwrap().vars.len(), 2);
+}
+
+#[test]
+fn test_key_rule_skips_invalid_keys() {
+    let content = "GOOD=1\nmy-key=2\nbad key=3\n1ABC=4\nx;Remove-Item=5";
+    let file = create_temp_env(content);
+    let path = file.path().to_str().unwrap();
+
+    let output = parse_env_file_with(path, &ParseOptions::default()).unwrap();
+    let keys: Vec<&str> = output.vars.iter().map(|v| v.key.as_str()).collect();
+    assert_eq!(keys, vec!["GOOD"]);
+    assert_eq!(output.diagnostics.len(), 4);
+    assert!(output.diagnostics.iter().all(|d| d.kind == DiagnosticKind::InvalidKey));
+
+    let options = ParseOptions { key_rule: KeyRule::Relaxed, ..Default::default() };
+    let output = parse_env_file_with(path, &options).unwrap();
+    let keys: Vec<&str> = output.vars.iter().map(|v| v.key.as_str()).collect();
+    assert_eq!(keys, vec!["GOOD", "my-key"]);
+
+    assert!(KeyRule::Any.allows("x;Remove-Item"));
+    assert!(!KeyRule::Any.allows("bad key"));
+}
+
+#[test]
+fn test_parse_from_str_reader_and_path() {
+    let vars = parse_env_str("A=1\nB=${A}2").unwrap();
+    assert_eq!(vars[1].value, "12");
+
+    let vars = parse_env_reader("A=from_reader".as_bytes()).unwrap();
+    assert_eq!(vars[0].value, "from_reader");
+
+    let file = create_temp_env("A=from_path");
+    let vars = parse_env_path(file.path().as_os_str()).unwrap();
+    assert_eq!(vars[0].value, "from_path");
+}
+
+#[test]
+fn test_parse_str_diagnostics_name_the_source() {
+    let output = parse_env_str_with("SKIP_ME", &ParseOptions::default()).unwrap();
+
+    assert_eq!(output.diagnostics[0].path.to_str(), Some("<string>"));
+}
+
+#[test]
+fn test_duplicate_policies() {
+    let content = "A=1\nB=${A}\nA=2\nC=${A}";
+    let values = |policy| {
+        let options = ParseOptions { duplicates: policy, ..Default::default() };
+        let output = parse_env_str_with(content, &options).unwrap();
+        let pairs: Vec<(String, String)> = output.vars.into_iter().map(|v| (v.key, v.value)).collect();
+        (pairs, output.diagnostics)
+    };
+
+    let (vars, diagnostics) = values(DuplicatePolicy::LastWins);
+    assert_eq!(vars, vec![("B".into(), "1".into()), ("A".into(), "2".into()), ("C".into(), "2".into())]);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].kind, DiagnosticKind::DuplicateKey);
+    assert_eq!((diagnostics[0].line, diagnostics[0].related_line), (3, Some(1)));
+
+    let (vars, _) = values(DuplicatePolicy::FirstWins);
+    assert_eq!(vars, vec![("A".into(), "1".into()), ("B".into(), "1".into()), ("C".into(), "1".into())]);
+
+    let options = ParseOptions { duplicates: DuplicatePolicy::Error, ..Default::default() };
+    let err = parse_env_str_with(content, &options).err().unwrap();
+    assert!(matches!(err, ParseError::DuplicateKey { line: 3, first_line: 1, .. }));
+}
+
+fn create_temp_env_bytes(bytes: &[u8]) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(bytes).unwrap();
+    file
+}
+
+fn utf16(text: &str, little_endian: bool, bom: bool) -> Vec<u8> {
+    let mut units: Vec<u16> = Vec::new();
+    if bom {
+        units.push(0xFEFF);
+    }
+    units.extend(text.encode_utf16());
+    units
+        .into_iter()
+        .flat_map(|u| if little_endian { u.to_le_bytes() } else { u.to_be_bytes() })
+        .collect()
+}
+
+#[test]
+fn test_utf8_bom_and_crlf() {
+    let file = create_temp_env_bytes(b"\xEF\xBB\xBFKEY=value\r\nMULTI=\"a\r\nb\"\r\n");
+    let vars = parse_env_file(file.path().to_str().unwrap()).unwrap();
+
+    assert_eq!(vars[0].key, "KEY");
+    assert_eq!(vars[0].value, "value");
+    assert_eq!(vars[1].value, "a\nb");
+}
+
+#[test]
+fn test_utf16_files() {
+    let text = "KEY=caf\u{e9}\r\nOTHER=2\r\n";
+    for (little_endian, bom) in [(true, true), (false, true), (true, false), (false, false)] {
+        let file = create_temp_env_bytes(&utf16(text, little_endian, bom));
+        let vars = parse_env_file(file.path().to_str().unwrap()).unwrap();
+
+        assert_eq!(vars.len(), 2, "le={} bom={}", little_endian, bom);
+        assert_eq!(vars[0].key, "KEY");
+        assert_eq!(vars[0].value, "caf\u{e9}");
+    }
+}
+
+#[test]
+fn test_forced_encoding_and_invalid_bytes() {
+    let file = create_temp_env_bytes(&utf16("A=1", true, false));
+    let options = ParseOptions { encoding: Some(Encoding::Utf16Le), ..Default::default() };
+    let vars = parse_env_file_with(file.path().to_str().unwrap(), &options).unwrap().vars;
+    assert_eq!(vars[0].value, "1");
+
+    let file = create_temp_env_bytes(b"A=\xFF\xFE\xFD");
+    let options = ParseOptions { encoding: Some(Encoding::Utf8), ..Default::default() };
+    let err = parse_env_file_with(file.path().to_str().unwrap(), &options).err().unwrap();
+    assert!(matches!(err, ParseError::Encoding { .. }));
+}
+
+#[test]
+fn test_command_substitution_is_opt_in() {
+    let content = "GREETING=$(echo hello)\nQUOTED=\"<$(echo hi)>\"\nLITERAL='$(echo no)'";
+
+    let output = parse_env_str_with(content, &ParseOptions::default()).unwrap();
+    assert_eq!(output.vars[0].value, "$(echo hello)");
+    assert_eq!(output.diagnostics.len(), 2);
+    assert_eq!(output.diagnostics[0].kind, DiagnosticKind::CommandSubstitution);
+    assert_eq!((output.diagnostics[1].line, output.diagnostics[1].column), (2, 10));
+
+    let options = ParseOptions { exec: Some(ExecOptions::default()), ..Default::default() };
+    let output = parse_env_str_with(content, &options).unwrap();
+    assert_eq!(output.vars[0].value, "hello");
+    assert_eq!(output.vars[1].value, "<hi>");
+    assert_eq!(output.vars[2].value, "$(echo no)");
+    assert!(output.diagnostics.is_empty());
+}
+
+#[test]
+fn test_failing_command_reports_line() {
+    let options = ParseOptions { exec: Some(ExecOptions::default()), ..Default::default() };
+    let err = parse_env_str_with("A=1\nB=$(exit 3)", &options).err().unwrap();
+
+    assert!(matches!(err, ParseError::Command { line: 2, column: 3, .. }));
+    assert!(err.to_string().contains("exited with status 3"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_command_timeout() {
+    let exec = ExecOptions { timeout: std::time::Duration::from_millis(200), ..Default::default() };
+    let options = ParseOptions { exec: Some(exec), ..Default::default() };
+    let err = parse_env_str_with("SLOW=$(sleep 5)", &options).err().unwrap();
+
+    assert!(err.to_string().contains("timed out"));
+
+    // The shell exits at once, but the background sleep still holds its output open
+    let started = std::time::Instant::now();
+    let err = parse_env_str_with("SLOW=$(sleep 5 & echo hi)", &options).err().unwrap();
+    assert!(err.to_string().contains("timed out"));
+    assert!(started.elapsed() < std::time::Duration::from_secs(3));
+}
+
+#[test]
+fn test_include_directives() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("shared")).unwrap();
+    std::fs::write(dir.path().join("shared/common.env"), "HOST=db\nPORT=5432\n\nURL=${SCHEME_MISSING}://x\n").unwrap();
+    std::fs::write(dir.path().join("shared/extra.env"), "EXTRA=1\n").unwrap();
+    std::fs::create_dir(dir.path().join("svc")).unwrap();
+    let env = dir.path().join("svc/.env");
+    std::fs::write(&env, "# @include ../shared/common.env\nsource \"../shared/extra.env\"\nPORT=6543\nDSN=${HOST}:${PORT}\n").unwrap();
+
+    let output = parse_env_file_with(env.to_str().unwrap(), &ParseOptions::default()).unwrap();
+    let pairs: Vec<(&str, &str)> = output.vars.iter().map(|v| (v.key.as_str(), v.value.as_str())).collect();
+    assert_eq!(
+        pairs,
+        vec![("HOST", "db"), ("URL", "${SCHEME_MISSING}://x"), ("EXTRA", "1"), ("PORT", "6543"), ("DSN", "db:6543")]
+    );
+
+    // Overriding an included key is not a duplicate; the warning points into the included file
+    assert_eq!(output.diagnostics.len(), 1);
+    assert!(output.diagnostics[0].path.ends_with("common.env"));
+    assert_eq!(output.diagnostics[0].line, 4);
+}
+
+#[test]
+fn test_include_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let a = dir.path().join("a.env");
+    std::fs::write(&a, "A=1\nsource b.env\n").unwrap();
+    std::fs::write(dir.path().join("b.env"), "B=2\n# @include a.env\n").unwrap();
+
+    let err = parse_env_path(&a).err().unwrap();
+    assert!(matches!(err, ParseError::Include { line: 2, column: 12, .. }));
+    assert!(err.path().ends_with("b.env"));
+    assert!(err.to_string().contains("include cycle detected"));
+
+    let err = parse_env_str("source ./does-not-exist.env").err().unwrap();
+    assert!(matches!(err, ParseError::Include { line: 1, column: 8, .. }));
+
+    // Look-alikes stay what they were
+    let vars = parse_env_str("# @included by the deploy script\nsource=git").unwrap();
+    assert_eq!(vars[0].key, "source");
+}
+
+#[test]
+fn test_duplicate_around_include() {
+    let dir = tempfile::tempdir().unwrap();
+    let env = dir.path().join(".env");
+    std::fs::write(&env, "X=1\nsource inc.env\nX=3\n").unwrap();
+    std::fs::write(dir.path().join("inc.env"), "X=2\n").unwrap();
+    let parse = |policy| {
+        let options = ParseOptions { duplicates: policy, ..Default::default() };
+        parse_env_file_with(env.to_str().unwrap(), &options)
+    };
+
+    let output = parse(DuplicatePolicy::LastWins).unwrap();
+    assert_eq!(output.vars.len(), 1);
+    assert_eq!((output.vars[0].value.as_str(), output.vars[0].line), ("3", 3));
+    assert_eq!(output.diagnostics.len(), 1);
+    assert_eq!((output.diagnostics[0].line, output.diagnostics[0].related_line), (3, Some(1)));
+
+    let output = parse(DuplicatePolicy::FirstWins).unwrap();
+    assert_eq!(output.vars.len(), 1);
+    assert_eq!((output.vars[0].value.as_str(), output.vars[0].line), ("1", 1));
+    assert_eq!(output.diagnostics.len(), 1);
+
+    let err = parse(DuplicatePolicy::Error).err().unwrap();
+    assert!(matches!(err, ParseError::DuplicateKey { line: 3, first_line: 1, .. }));
+}
+
+#[test]
+fn test_mode_cascade() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path().join(".env");
+    std::fs::write(&base, "A=base\nB=base\nC=base\n").unwrap();
+    std::fs::write(dir.path().join(".env.local"), "B=local\n").unwrap();
+    std::fs::write(dir.path().join(".env.production"), "C=prod\nURL=${B}/${C}\n").unwrap();
+
+    let files = mode_files(&base, "production");
+    let names: Vec<String> = files.iter().map(|f| f.path.file_name().unwrap().to_string_lossy().into_owned()).collect();
+    assert_eq!(names, vec![".env", ".env.local", ".env.production", ".env.production.local"]);
+    assert!(files.iter().all(|f| f.optional));
+
+    let vars = parse_env_files_with(&files, &ParseOptions::default()).unwrap().vars;
+    let pairs: Vec<(&str, &str)> = vars.iter().map(|v| (v.key.as_str(), v.value.as_str())).collect();
+    assert_eq!(pairs, vec![("A", "base"), ("B", "local"), ("C", "prod"), ("URL", "local/prod")]);
+
+    let err = parse_env_files_with(&mode_files(dir.path().join("missing.env"), "dev"), &ParseOptions::default());
+    assert!(matches!(err, Err(ParseError::NotFound { .. })));
+    let err = parse_env_files_with(&[EnvFile::optional(&base), EnvFile::required(dir.path().join("nope"))], &ParseOptions::default());
+    assert!(matches!(err, Err(ParseError::NotFound { .. })));
+}
+
+#[test]
+fn test_dialects() {
+    let content = concat!(
+        "export HOST=db\n",
+        "URL=http://$HOST/#top\n",
+        "BRACED=${HOST}:${PORT:-5432}\n",
+        "ASSIGN=${NEW:=x}\n",
+        "ESC=\"a\\tb\\n\\$HOST\\q\"\n",
+        "TICK=`raw $HOST`\n",
+        "; systemd comment\n",
+        "CONT=one \\\n",
+        "two\n",
+    );
+    let values = |dialect| {
+        let options = ParseOptions { dialect, ..Default::default() };
+        let output = parse_env_str_with(content, &options).unwrap();
+        output.vars.into_iter().map(|v| (v.key, v.value)).collect::<std::collections::HashMap<_, _>>()
+    };
+
+    let compose = values(Dialect::Compose);
+    assert_eq!(compose["URL"], "http://db/#top");
+    assert_eq!(compose["BRACED"], "db:5432");
+    assert_eq!(compose["ASSIGN"], "${NEW:=x}");
+    assert_eq!(compose["ESC"], "a\tb\n$HOST\\q");
+
+    let python = values(Dialect::Python);
+    assert_eq!(python["URL"], "http://$HOST/#top");
+    assert_eq!(python["BRACED"], "db:5432");
+
+    // Only python-dotenv reads \' inside single quotes; elsewhere the quote closes early
+    let single = "SINGLE='it\\'s'";
+    let options = ParseOptions { dialect: Dialect::Python, ..Default::default() };
+    assert_eq!(parse_env_str_with(single, &options).unwrap().vars[0].value, "it's");
+    assert!(matches!(parse_env_str(single), Err(ParseError::Syntax { line: 1, column: 13, .. })));
+
+    let node = values(Dialect::Node);
+    assert_eq!(node["URL"], "http://$HOST/");
+    assert_eq!(node["BRACED"], "${HOST}:${PORT:-5432}");
+    assert_eq!(node["ESC"], "a\\tb\n\\$HOST\\q");
+    assert_eq!(node["TICK"], "raw $HOST");
+
+    let ruby = values(Dialect::Ruby);
+    assert_eq!(ruby["URL"], "http://db/#top");
+    assert_eq!(ruby["ESC"], "atb\n$HOSTq");
+    assert_eq!(ruby["BRACED"], "db:${PORT:-5432}");
+
+    let systemd = values(Dialect::Systemd);
+    assert!(!systemd.contains_key("HOST"));
+    assert_eq!(systemd["URL"], "http://$HOST/#top");
+    assert_eq!(systemd["ESC"], "a\\tb\\n$HOST\\q");
+    assert_eq!(systemd["CONT"], "one two");
+}
+
+#[test]
+fn test_dialect_unresolved_references_become_empty() {
+    let options = ParseOptions { dialect: Dialect::Compose, ..Default::default() };
+    let output = parse_env_str_with("A=x${MISSING_RUSTER_VAR}y", &options).unwrap();
+
+    assert_eq!(output.vars[0].value, "xy");
+    assert!(output.diagnostics[0].message.contains("expanded to an empty string"));
+}
+
+#[test]
+fn test_var_metadata() {
+    let content = "HOST=localhost # dev\n\n  export URL=\"http://${HOST}:\\u{3a}\"\nRAW='lit ${HOST}'\n";
+    let file = create_temp_env(content);
+    let vars = parse_env_file(file.path().to_str().unwrap()).unwrap();
+
+    assert_eq!(vars[0].raw, "localhost");
+    assert_eq!(vars[0].quote, QuoteStyle::None);
+    assert!(!vars[0].export);
+    assert_eq!((vars[0].line, vars[0].column), (1, 1));
+
+    assert_eq!(vars[1].key, "URL");
+    assert_eq!(vars[1].raw, "\"http://${HOST}:\\u{3a}\"");
+    assert_eq!(vars[1].quote, QuoteStyle::Double);
+    assert!(vars[1].export);
+    assert_eq!((vars[1].line, vars[1].column), (3, 10));
+    assert_eq!(vars[1].path, file.path());
+
+    assert_eq!(vars[2].raw, "'lit ${HOST}'");
+    assert_eq!(vars[2].quote, QuoteStyle::Single);
+    assert!(vars[2].references.is_empty());
+}
+
+#[test]
+fn test_reference_sources() {
+    std::env::set_var("RUSTER_REF_SYSTEM", "sys");
+    let content = "\
+A=1
+B=${A}-$RUSTER_REF_SYSTEM-${RUSTER_REF_UNSET:-d}-${RUSTER_REF_NONE}-${A}
+C=${RUSTER_REF_ASSIGNED:=x}
+D=$RUSTER_REF_ASSIGNED
+";
+    let file = create_temp_env(content);
+    let path = file.path().to_path_buf();
+
+    for order_independent in [false, true] {
+        let options = ParseOptions { order_independent, ..Default::default() };
+        let vars = parse_env_file_with(path.to_str().unwrap(), &options).unwrap().vars;
+
+        let reference = |name: &str, source| Reference { name: name.to_string(), source };
+        assert_eq!(
+            vars[1].references,
+            vec![
+                reference("A", ReferenceSource::File { path: path.clone(), line: 1 }),
+                reference("RUSTER_REF_SYSTEM", ReferenceSource::System),
+                reference("RUSTER_REF_UNSET", ReferenceSource::Default),
+                reference("RUSTER_REF_NONE", ReferenceSource::Unset),
+            ]
+        );
+        assert_eq!(vars[2].references, vec![reference("RUSTER_REF_ASSIGNED", ReferenceSource::Default)]);
+        assert_eq!(vars[3].references, vec![reference("RUSTER_REF_ASSIGNED", ReferenceSource::Default)]);
+    }
+}